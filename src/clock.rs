@@ -0,0 +1,227 @@
+//! Reads clock correction files, which take TOAs from the clock of an
+//! observatory towards a realisation of TT.
+//!
+//! # Examples
+//!
+//! ```
+//! # use psrutils::clock::{ClockChain, ClockFile};
+//! # use psrutils::data_types::Mjd;
+//! # fn test() -> Result<(), psrutils::error::PsruError> {
+//! let gbt = "\
+//! ## UTC(GBT) UTC(GPS)
+//! 55000.0 1.0e-6
+//! 55010.0 2.0e-6
+//! ";
+//! let gps = "\
+//! ## UTC(GPS) UTC
+//! 54000.0 -1.0e-8
+//! 56000.0 -1.0e-8
+//! ";
+//! let files = vec![
+//!     ClockFile::read_tempo2(gbt.as_bytes())?,
+//!     ClockFile::read_tempo2(gps.as_bytes())?,
+//! ];
+//!
+//! let chain = ClockChain::new(files)?;
+//! let mjd = "55005.0".parse::<Mjd>()?;
+//! assert_eq!(chain.correction(mjd)?.picoseconds(), 1_490_000);
+//!
+//! let late = "55011.0".parse::<Mjd>()?;
+//! assert!(chain.correction(late).is_err());
+//! # Ok(())
+//! # }
+//! # test().unwrap();
+//! ```
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::data_types::{Mjd, TimeDelta};
+use crate::error::PsruError;
+
+pub use chain::ClockChain;
+
+mod chain;
+mod tests;
+
+/// The corrections from one clock to another, e.g. from `UTC(GBT)` to
+/// `UTC(GPS)`, at a series of MJDs. In between those, the corrections are
+/// interpolated linearly.
+///
+/// Two points at the same MJD make a step, with the later one used from
+/// that MJD on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClockFile {
+    /// The clock corrected from.
+    pub from: String,
+    /// The clock corrected to.
+    pub to: String,
+    /// The MJDs and corrections (s), in order.
+    points: Vec<(Mjd, f64)>,
+}
+impl ClockFile {
+    /// Corrections from one clock to another, with the corrections (s) to
+    /// add at each MJD.
+    ///
+    /// # Errors
+    /// Fails with [`PsruError::Unparsable`] if the MJDs are not in order, or
+    /// if a correction is not finite.
+    pub fn new(
+        from: impl Into<String>,
+        to: impl Into<String>,
+        points: Vec<(Mjd, f64)>,
+    ) -> Result<Self, PsruError> {
+        if let Some(w) = points.windows(2).find(|w| w[0].0 > w[1].0) {
+            let line = format!("{} {}", w[1].0, w[1].1);
+            return Err(PsruError::unparsable(&line, "clock correction"));
+        }
+        if let Some((mjd, correction)) =
+            points.iter().find(|(_, c)| !c.is_finite())
+        {
+            let line = format!("{mjd} {correction}");
+            return Err(PsruError::unparsable(&line, "clock correction"));
+        }
+
+        Ok(Self {
+            from: from.into(),
+            to: to.into(),
+            points,
+        })
+    }
+
+    /// Reads a Tempo2 `.clk` file. The first line names the clocks, like
+    /// `# UTC(GBT) UTC(GPS)`, and every other line is either a comment
+    /// starting with `#`, or an MJD and the correction (s), which may be
+    /// followed by more columns.
+    ///
+    /// # Errors
+    /// Fails if the clocks are not named, if a line can not be read, or if
+    /// the MJDs are not in order.
+    pub fn read_tempo2(reader: impl BufRead) -> Result<Self, PsruError> {
+        let mut lines = reader.lines();
+        let header = lines.next().transpose()?.unwrap_or_default();
+        let clocks = header
+            .trim_start()
+            .strip_prefix('#')
+            .map(|names| names.split_whitespace().collect::<Vec<_>>());
+        let Some([from, to, ..]) = clocks.as_deref() else {
+            return Err(PsruError::unparsable(&header, "clock names"));
+        };
+
+        let mut points = Vec::new();
+        for line in lines {
+            let line = line?;
+            let err = || PsruError::unparsable(&line, "clock correction");
+            if line.trim_start().starts_with('#') {
+                continue;
+            }
+
+            let parts = line.split_whitespace().collect::<Vec<_>>();
+            let (mjd, correction) = match parts[..] {
+                [] => continue,
+                [mjd, correction, ..] => (mjd, correction),
+                _ => return Err(err()),
+            };
+            points.push((
+                mjd.parse().map_err(|_| err())?,
+                correction.parse().map_err(|_| err())?,
+            ));
+        }
+
+        Self::new(*from, *to, points)
+    }
+
+    /// Reads the corrections of one site from a TEMPO `time.dat` file,
+    /// which has columns of the MJD, two offsets (µs), and the site code.
+    /// The correction is the second offset less the first, from the site
+    /// clock to UTC.
+    ///
+    /// Lines that do not start with an MJD, e.g. headers, are skipped.
+    ///
+    /// # Errors
+    /// Fails if the offsets of a line can not be read, or if the MJDs of
+    /// the site are not in order.
+    pub fn read_tempo(
+        reader: impl BufRead,
+        site: &str,
+    ) -> Result<Self, PsruError> {
+        let mut points = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            let err = || PsruError::unparsable(&line, "clock correction");
+            let column = |from: usize, to: usize| {
+                line.get(from..to.min(line.len()))
+                    .unwrap_or_default()
+                    .trim()
+            };
+
+            let Ok(mjd) = column(0, 9).parse::<Mjd>() else {
+                continue;
+            };
+            if !column(34, 35).eq_ignore_ascii_case(site) {
+                continue;
+            }
+
+            let first = column(9, 21).parse::<f64>().map_err(|_| err())?;
+            let second = column(21, 33).parse::<f64>().map_err(|_| err())?;
+            points.push((mjd, (second - first) * 1e-6));
+        }
+
+        Self::new(site, "UTC", points)
+    }
+
+    /// Reads a Tempo2 `.clk` file from the filesystem, like
+    /// [`ClockFile::read_tempo2`].
+    ///
+    /// # Errors
+    /// Will throw errors for bad files or contents.
+    pub fn read_path(path: &Path) -> Result<Self, PsruError> {
+        Self::read_tempo2(BufReader::new(File::open(path)?))
+    }
+
+    /// The MJDs and corrections (s), in order.
+    pub fn points(&self) -> &[(Mjd, f64)] {
+        &self.points
+    }
+
+    /// The first and last MJDs with corrections, if there are any.
+    pub fn span(&self) -> Option<(Mjd, Mjd)> {
+        self.points
+            .first()
+            .zip(self.points.last())
+            .map(|(a, b)| (a.0, b.0))
+    }
+
+    /// The correction to add at an MJD, interpolated between the nearest
+    /// points.
+    ///
+    /// # Errors
+    /// Fails with [`PsruError::ClockOutOfRange`] if the MJD is before the
+    /// first point or after the last, and with [`PsruError::Unparsable`] if
+    /// the correction is too large to be a [`TimeDelta`].
+    pub fn correction(&self, mjd: Mjd) -> Result<TimeDelta, PsruError> {
+        let after = self.points.partition_point(|(m, _)| *m <= mjd);
+        let seconds = match (after.checked_sub(1), self.points.get(after)) {
+            (Some(before), Some(next)) => {
+                let (m0, c0) = self.points[before];
+                let part = (mjd - m0).as_seconds() / (next.0 - m0).as_seconds();
+                (next.1 - c0).mul_add(part, c0)
+            }
+            (Some(last), None) if self.points[last].0 == mjd => {
+                self.points[last].1
+            }
+            _ => {
+                return Err(PsruError::ClockOutOfRange {
+                    mjd,
+                    clocks: format!("{} -> {}", self.from, self.to),
+                    span: self.span(),
+                });
+            }
+        };
+
+        TimeDelta::checked_from_seconds(seconds).ok_or_else(|| {
+            PsruError::unparsable(&seconds.to_string(), "clock correction")
+        })
+    }
+}
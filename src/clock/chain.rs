@@ -0,0 +1,152 @@
+use std::collections::VecDeque;
+use std::path::Path;
+
+use super::ClockFile;
+use crate::data_types::{Mjd, TimeDelta};
+use crate::error::PsruError;
+use crate::parfile::Parfile;
+
+/// Clock corrections made one after another, e.g. from an observatory to
+/// GPS, to UTC, and then to `TT(BIPM2021)`. Each file corrects from the
+/// clock the one before it corrects to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClockChain {
+    files: Vec<ClockFile>,
+}
+impl ClockChain {
+    /// Chains clock corrections in the order given. Clocks are matched
+    /// regardless of case.
+    ///
+    /// # Errors
+    /// Fails with [`PsruError::ClockChainBroken`] if there are no files, or
+    /// if one does not correct from the clock of the one before it.
+    pub fn new(files: Vec<ClockFile>) -> Result<Self, PsruError> {
+        if files.is_empty() {
+            return Err(PsruError::ClockChainBroken(String::from(
+                "no clock files",
+            )));
+        }
+        if let Some(w) = files.windows(2).find(|w| !same(&w[0].to, &w[1].from))
+        {
+            return Err(PsruError::ClockChainBroken(format!(
+                "'{}' does not go on from '{}'",
+                w[1].from, w[0].to
+            )));
+        }
+
+        Ok(Self { files })
+    }
+
+    /// Finds the shortest chain from one clock to another among some clock
+    /// files, e.g. all those in a clock directory.
+    ///
+    /// # Errors
+    /// Fails with [`PsruError::ClockChainBroken`] if there is no such chain.
+    pub fn find(
+        files: &[ClockFile],
+        from: &str,
+        to: &str,
+    ) -> Result<Self, PsruError> {
+        // Breadth first, remembering which file led to each one
+        let mut led_by = vec![None; files.len()];
+        let mut queue = VecDeque::new();
+        for (i, file) in files.iter().enumerate() {
+            if same(&file.from, from) {
+                led_by[i] = Some(i);
+                queue.push_back(i);
+            }
+        }
+
+        while let Some(i) = queue.pop_front() {
+            if same(&files[i].to, to) {
+                let mut chain = vec![files[i].clone()];
+                let mut at = i;
+                while let Some(before) = led_by[at].filter(|b| *b != at) {
+                    chain.push(files[before].clone());
+                    at = before;
+                }
+                chain.reverse();
+                return Self::new(chain);
+            }
+
+            for (j, file) in files.iter().enumerate() {
+                if led_by[j].is_none() && same(&file.from, &files[i].to) {
+                    led_by[j] = Some(i);
+                    queue.push_back(j);
+                }
+            }
+        }
+
+        Err(PsruError::ClockChainBroken(format!(
+            "no way from '{from}' to '{to}'"
+        )))
+    }
+
+    /// The files, in the order they are applied.
+    pub fn files(&self) -> &[ClockFile] {
+        &self.files
+    }
+
+    /// The clock the chain corrects from.
+    pub fn from(&self) -> &str {
+        &self.files[0].from
+    }
+
+    /// The clock the chain corrects to.
+    pub fn to(&self) -> &str {
+        &self.files[self.files.len() - 1].to
+    }
+
+    /// The sum of the corrections at an MJD. Each file is interpolated at
+    /// the MJD as corrected by the files before it.
+    ///
+    /// # Errors
+    /// Fails with [`PsruError::ClockOutOfRange`] if any file does not cover
+    /// the MJD.
+    pub fn correction(&self, mjd: Mjd) -> Result<TimeDelta, PsruError> {
+        self.files.iter().try_fold(TimeDelta::ZERO, |sum, file| {
+            let at = mjd.checked_add(sum).unwrap_or(mjd);
+            Ok(sum + file.correction(at)?)
+        })
+    }
+}
+
+impl Parfile {
+    /// Reads the clock files of `CLK_CORR_CHAIN` from a directory, and
+    /// chains them. If `CLK` is also given, the chain must end at it.
+    ///
+    /// Gives `None` if there is no `CLK_CORR_CHAIN`.
+    ///
+    /// # Errors
+    /// Fails if a file can not be read, or if the files do not make a chain
+    /// to `CLK`.
+    pub fn clock_chain(
+        &self,
+        dir: &Path,
+    ) -> Result<Option<ClockChain>, PsruError> {
+        let Some(names) = self.text("CLK_CORR_CHAIN") else {
+            return Ok(None);
+        };
+
+        let files = names
+            .split_whitespace()
+            .map(|name| ClockFile::read_path(&dir.join(name)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let chain = ClockChain::new(files)?;
+
+        match self.text("CLK") {
+            Some(clock) if !same(chain.to(), clock) => {
+                Err(PsruError::ClockChainBroken(format!(
+                    "ends at '{}', not at CLK '{clock}'",
+                    chain.to()
+                )))
+            }
+            _ => Ok(Some(chain)),
+        }
+    }
+}
+
+/// Whether two clocks are the same, regardless of case.
+const fn same(a: &str, b: &str) -> bool {
+    a.eq_ignore_ascii_case(b)
+}
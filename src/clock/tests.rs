@@ -0,0 +1,185 @@
+#[allow(unused)]
+use super::*;
+#[allow(unused)]
+use crate::parfile::Parfile;
+#[cfg(test)]
+use crate::test_dir::TestDir;
+
+#[test]
+fn tempo2_files() {
+    let mjd = |text: &str| text.parse::<Mjd>().unwrap();
+    let text = "\
+# UTC(AO) UTC(GPS)
+# Some comment
+50000.0  1.0e-6   0.1
+50010.0  3.0e-6
+
+# A step at 50020
+50020.0  3.0e-6
+50020.0  5.0e-6
+50030.0  5.0e-6
+";
+    let file = ClockFile::read_tempo2(text.as_bytes()).unwrap();
+    assert_eq!(
+        (file.from.as_str(), file.to.as_str()),
+        ("UTC(AO)", "UTC(GPS)")
+    );
+    assert_eq!(file.points().len(), 5);
+    assert_eq!(file.span(), Some((mjd("50000.0"), mjd("50030.0"))));
+
+    let at = |text| file.correction(mjd(text)).unwrap().picoseconds();
+    assert_eq!(at("50000.0"), 1_000_000);
+    assert_eq!(at("50002.5"), 1_500_000);
+    assert_eq!(at("50019.9"), 3_000_000);
+    assert_eq!(at("50020.0"), 5_000_000);
+    assert_eq!(at("50030.0"), 5_000_000);
+
+    for outside in ["49999.9", "50030.1"] {
+        let err = file.correction(mjd(outside)).unwrap_err();
+        assert!(matches!(err, PsruError::ClockOutOfRange { .. }));
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "MJD {outside} is not covered by the clock file UTC(AO) -> \
+                 UTC(GPS), which spans MJD 50000.0 to 50030.0."
+            )
+        );
+    }
+
+    for bad in [
+        "50000.0 1.0e-6\n",
+        "#\n50000.0 1.0e-6\n",
+        "# A B\n50000.0\n",
+        "# A B\n50000.0 one\n",
+        "# A B\n50010.0 1.0\n50000.0 1.0\n",
+        "# A B\n50000.0 inf\n",
+        "# A B\n50000.0 NaN\n",
+    ] {
+        let result = ClockFile::read_tempo2(bad.as_bytes());
+        assert!(matches!(result, Err(PsruError::Unparsable { .. })), "{bad}");
+    }
+
+    let empty = ClockFile::read_tempo2(&b"# A B\n"[..]).unwrap();
+    assert!(empty.correction(mjd("50000.0")).is_err());
+}
+
+#[test]
+fn tempo_files() {
+    let mjd = |text: &str| text.parse::<Mjd>().unwrap();
+    let text = "\
+   MJD       EECO-REF    NIST-REF NS      DATE    COMMENTS
+=========    ========    ======== ==    ========  ========
+ 50000.00       0.000       1.500 3
+ 50000.00       0.000       9.000 a
+ 50010.00       1.000       4.500 3
+ 50010.00       0.000       9.000 a
+";
+    let file = ClockFile::read_tempo(text.as_bytes(), "3").unwrap();
+    assert_eq!(file.from, "3");
+    assert_eq!(file.points().len(), 2);
+    let correction = file.correction(mjd("50005.0")).unwrap();
+    assert_eq!(correction.picoseconds(), 2_500_000);
+
+    let other = ClockFile::read_tempo(text.as_bytes(), "A").unwrap();
+    assert_eq!(
+        other.correction(mjd("50005.0")).unwrap().picoseconds(),
+        9_000_000
+    );
+
+    let bad = " 50000.00        zero       1.500 3\n";
+    let result = ClockFile::read_tempo(bad.as_bytes(), "3");
+    assert!(matches!(result, Err(PsruError::Unparsable { .. })));
+}
+
+#[test]
+fn chains() {
+    let mjd = |text: &str| text.parse::<Mjd>().unwrap();
+    let file = |from: &str, to: &str, correction: f64| {
+        let points =
+            vec![(mjd("50000.0"), correction), (mjd("60000.0"), correction)];
+        ClockFile::new(from, to, points).unwrap()
+    };
+    let files = [
+        file("UTC(GPS)", "UTC", 1e-9),
+        file("TAI", "TT(BIPM2021)", 3e-8),
+        file("UTC(AO)", "UTC(GPS)", 1e-6),
+        file("UTC", "TAI", 0.0),
+        file("UTC(GBT)", "UTC(GPS)", 2e-6),
+        file("UTC", "TT(BIPM2019)", 0.0),
+    ];
+
+    let chain = ClockChain::find(&files, "utc(ao)", "TT(BIPM2021)").unwrap();
+    let clocks = chain
+        .files()
+        .iter()
+        .map(|f| f.to.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(clocks, ["UTC(GPS)", "UTC", "TAI", "TT(BIPM2021)"]);
+    assert_eq!((chain.from(), chain.to()), ("UTC(AO)", "TT(BIPM2021)"));
+    let correction = chain.correction(mjd("55000.0")).unwrap();
+    assert_eq!(correction.picoseconds(), 1_031_000);
+
+    let chain = ClockChain::find(&files, "UTC(GBT)", "TT(BIPM2019)").unwrap();
+    assert_eq!(chain.files().len(), 3);
+    assert!(matches!(
+        chain.correction(mjd("60000.5")),
+        Err(PsruError::ClockOutOfRange { .. })
+    ));
+
+    for (from, to) in [("UTC(GBT)", "UTC(AO)"), ("UTC(JB)", "UTC")] {
+        assert!(matches!(
+            ClockChain::find(&files, from, to),
+            Err(PsruError::ClockChainBroken(_))
+        ));
+    }
+    assert!(matches!(
+        ClockChain::new(vec![files[0].clone(), files[2].clone()]),
+        Err(PsruError::ClockChainBroken(_))
+    ));
+    assert!(ClockChain::new(Vec::new()).is_err());
+}
+
+#[test]
+fn parfile_chain() {
+    let mjd = |text: &str| text.parse::<Mjd>().unwrap();
+    let dir = TestDir::new("clock-chain");
+    std::fs::write(
+        dir.join("ao2gps.clk"),
+        "# UTC(AO) UTC(GPS)\n50000 1e-6\n60000 1e-6\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("gps2utc.clk"),
+        "# UTC(GPS) UTC\n50000 1e-9\n60000 1e-9\n",
+    )
+    .unwrap();
+
+    let par = |extra: &str| {
+        let text = format!("PSR J0000+0000\nF0 1\nPEPOCH 50000\nDM 1\n{extra}");
+        Parfile::read(text.as_bytes()).unwrap()
+    };
+
+    assert_eq!(par("").clock_chain(&dir).unwrap(), None);
+
+    let chain = par("CLK_CORR_CHAIN ao2gps.clk gps2utc.clk\n")
+        .clock_chain(&dir)
+        .unwrap()
+        .unwrap();
+    assert_eq!((chain.from(), chain.to()), ("UTC(AO)", "UTC"));
+    assert_eq!(
+        chain.correction(mjd("50000")).unwrap().picoseconds(),
+        1_001_000
+    );
+
+    let wrong =
+        par("CLK TT(BIPM2021)\nCLK_CORR_CHAIN ao2gps.clk gps2utc.clk\n");
+    assert!(matches!(
+        wrong.clock_chain(&dir),
+        Err(PsruError::ClockChainBroken(_))
+    ));
+    let missing = par("CLK_CORR_CHAIN ao2gps.clk nowhere.clk\n");
+    assert!(matches!(
+        missing.clock_chain(&dir),
+        Err(PsruError::IOError(_))
+    ));
+}
@@ -1,8 +1,14 @@
 //! Contains useful datatypes.
 
+mod calendar;
 mod j2000;
+mod leap;
 mod mjd;
+mod scale;
 mod tests;
 
+pub use calendar::{DateTime, Jd};
 pub use j2000::{DECCoordType, J2000Coord, J2000Dec, J2000Ra, RACoordType};
-pub use mjd::Mjd;
+pub use leap::LeapSeconds;
+pub use mjd::{Mjd, TimeDelta};
+pub use scale::{Epoch, Tai, Tcb, Tcg, Tdb, TimeScale, Tt, Utc};
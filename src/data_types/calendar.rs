@@ -0,0 +1,421 @@
+use std::str::FromStr;
+
+use super::mjd::{PS_PER_DAY, PS_PER_SECOND, decimal, fraction};
+use super::{LeapSeconds, Mjd, TimeDelta};
+use crate::error::PsruError;
+
+/// The MJD of 1970-01-01, which the day counts below are relative to.
+const UNIX_EPOCH: i64 = 40_587;
+/// The whole days between MJD 0 and JD 0, plus half a day.
+const JD_OFFSET: u64 = 2_400_000;
+/// The digits of a picosecond.
+const PS_DIGITS: usize = 12;
+
+/// A date and time of day in the Gregorian calendar, as in UTC.
+///
+/// It is read and written in ISO 8601, like `2016-12-31T23:59:60.25`, and
+/// converts to and from [`Mjd`]. An MJD on a day that ends with a leap
+/// second counts 86401 seconds to that day, so that `23:59:60` is a time
+/// like any other. The leap seconds are those of [`LeapSeconds::builtin`],
+/// also past its expiry.
+///
+/// An `Mjd` converted to a `DateTime` and back is always the same. On days
+/// with a leap second, a `DateTime` is converted to the nearest picosecond
+/// of the MJD, which are slightly longer than those of the day, so that
+/// date-times a picosecond apart may give the same MJD.
+///
+/// ```
+/// # use psrutils::data_types::{DateTime, Mjd};
+/// let date = "2016-12-31T23:59:60.5Z".parse::<DateTime>().unwrap();
+/// let mjd = Mjd::try_from(date).unwrap();
+/// assert_eq!(DateTime::from(mjd), date);
+/// assert_eq!(date.to_string(), "2016-12-31T23:59:60.5");
+///
+/// // Ordinal dates are read, and written with `#`
+/// let date = "2024-060T12:00".parse::<DateTime>().unwrap();
+/// assert_eq!(date.to_string(), "2024-02-29T12:00:00");
+/// assert_eq!(format!("{date:#.3}"), "2024-060T12:00:00.000");
+/// ```
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime {
+    pub year: i32,
+    /// 1 to 12.
+    pub month: u8,
+    /// From 1.
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    /// Up to 60 in a leap second.
+    pub second: u8,
+    /// The picoseconds into the second.
+    pub picosecond: u64,
+}
+
+/// Writes the date-time in ISO 8601. With a precision, that many digits of
+/// the second are written (cut short), and otherwise as many as are needed.
+/// The alternate form writes the day of the year instead of the month and
+/// day.
+impl std::fmt::Display for DateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if (0..=9999).contains(&self.year) {
+            write!(f, "{:04}", self.year)?;
+        } else {
+            write!(f, "{:+05}", self.year)?;
+        }
+        if f.alternate() {
+            write!(f, "-{:03}", self.day_of_year())?;
+        } else {
+            write!(f, "-{:02}-{:02}", self.month, self.day)?;
+        }
+        write!(f, "T{:02}:{:02}:{:02}", self.hour, self.minute, self.second)?;
+
+        let digits = format!("{:012}", self.picosecond);
+        let digits = f.precision().map_or_else(
+            || digits.trim_end_matches('0'),
+            |precision| &digits[..precision.min(PS_DIGITS)],
+        );
+        if !digits.is_empty() {
+            write!(f, ".{digits}")?;
+        }
+
+        Ok(())
+    }
+}
+impl FromStr for DateTime {
+    type Err = PsruError;
+
+    /// Reads calendar (`2024-02-29`) or ordinal (`2024-060`) dates, then
+    /// optionally a time as `12:00`, `12:00:00` or `12:00:00.5`, after a `T`
+    /// or a space. A `Z` at the end is allowed.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || PsruError::unparsable(s, "DateTime");
+        let number = |text: &str, len: usize| {
+            let ok =
+                text.len() == len && text.bytes().all(|b| b.is_ascii_digit());
+            ok.then(|| text.parse::<u16>().ok())
+                .flatten()
+                .ok_or_else(err)
+        };
+
+        let text = s.strip_suffix('Z').unwrap_or(s);
+        let (date, time) = text.split_once(['T', ' ']).unwrap_or((text, ""));
+
+        let sign = if date.starts_with('-') { -1 } else { 1 };
+        let date = date.strip_prefix(['+', '-']).unwrap_or(date);
+        let parts = date.split('-').collect::<Vec<_>>();
+        let year = match parts.first() {
+            Some(y)
+                if y.len() >= 4 && y.bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                sign * y.parse::<i32>().map_err(|_| err())?
+            }
+            _ => return Err(err()),
+        };
+        let mut date = match parts[1..] {
+            [month, day] => Self::date(
+                year,
+                u8::try_from(number(month, 2)?).map_err(|_| err())?,
+                u8::try_from(number(day, 2)?).map_err(|_| err())?,
+            ),
+            [day] => {
+                Self::from_day_of_year(year, number(day, 3)?).ok_or_else(err)?
+            }
+            _ => return Err(err()),
+        };
+
+        if !time.is_empty() {
+            let (hms, frac) = time.split_once('.').unwrap_or((time, ""));
+            let fields = hms
+                .split(':')
+                .map(|field| number(field, 2))
+                .collect::<Result<Vec<_>, _>>()?;
+            let (hour, minute, second) = match fields[..] {
+                [hour, minute] => (hour, minute, 0),
+                [hour, minute, second] => (hour, minute, second),
+                _ => return Err(err()),
+            };
+            if hms.len() < 8 && time.contains('.')
+                || frac.len() > PS_DIGITS
+                || !frac.bytes().all(|b| b.is_ascii_digit())
+            {
+                return Err(err());
+            }
+
+            date.hour = u8::try_from(hour).map_err(|_| err())?;
+            date.minute = u8::try_from(minute).map_err(|_| err())?;
+            date.second = u8::try_from(second).map_err(|_| err())?;
+            date.picosecond =
+                format!("{frac:0<12}").parse().unwrap_or_default();
+        }
+
+        date.is_valid().then_some(date).ok_or_else(err)
+    }
+}
+impl DateTime {
+    /// Midnight at the start of a day.
+    pub const fn date(year: i32, month: u8, day: u8) -> Self {
+        Self {
+            year,
+            month,
+            day,
+            hour: 0,
+            minute: 0,
+            second: 0,
+            picosecond: 0,
+        }
+    }
+
+    /// Midnight at the start of a day of the year, which counts from 1.
+    /// Gives `None` if the year does not have that day.
+    pub fn from_day_of_year(year: i32, day: u16) -> Option<Self> {
+        let first = days_from_civil(year, 1, 1);
+        let (y, month, day) = civil_from_days(first + i64::from(day) - 1);
+        (y == year).then(|| Self::date(year, month, day))
+    }
+
+    /// The day of the year, counting from 1.
+    pub fn day_of_year(&self) -> u16 {
+        let days = days_from_civil(self.year, self.month, self.day)
+            - days_from_civil(self.year, 1, 1);
+        u16::try_from(days + 1).unwrap_or_default()
+    }
+
+    /// Whether the year has a 29th of February.
+    pub const fn is_leap_year(&self) -> bool {
+        self.year % 4 == 0 && (self.year % 100 != 0 || self.year % 400 == 0)
+    }
+
+    /// The date-time as picoseconds into the day.
+    fn picoseconds(&self) -> u64 {
+        let seconds = (u64::from(self.hour) * 60 + u64::from(self.minute)) * 60
+            + u64::from(self.second);
+        seconds * PS_PER_SECOND + self.picosecond
+    }
+
+    /// Whether all the fields are in range, allowing any day to have a leap
+    /// second.
+    fn is_valid(&self) -> bool {
+        let days = match self.month {
+            2 if self.is_leap_year() => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        };
+
+        (1..=12).contains(&self.month)
+            && (1..=days).contains(&self.day)
+            && self.hour < 24
+            && self.minute < 60
+            && (self.second < 60
+                || self.second == 60 && self.hour == 23 && self.minute == 59)
+            && self.picosecond < PS_PER_SECOND
+    }
+}
+
+impl From<Mjd> for DateTime {
+    fn from(mjd: Mjd) -> Self {
+        let (year, month, day) =
+            civil_from_days(i64::from(mjd.int()) - UNIX_EPOCH);
+
+        let length = LeapSeconds::builtin().day_length(mjd.int());
+        let ps = utc_picoseconds(mjd, length);
+        let seconds = ps / PS_PER_SECOND;
+        #[allow(clippy::cast_possible_truncation)]
+        let (hour, minute, second) = if seconds >= 86_400 {
+            (23, 59, (seconds - 86_340) as u8)
+        } else {
+            (
+                (seconds / 3600) as u8,
+                (seconds / 60 % 60) as u8,
+                (seconds % 60) as u8,
+            )
+        };
+
+        Self {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            picosecond: ps % PS_PER_SECOND,
+        }
+    }
+}
+impl TryFrom<DateTime> for Mjd {
+    type Error = PsruError;
+
+    /// Gives an error if any field is out of range, if there is a leap
+    /// second on a day without one, or if the date is before MJD 0.
+    fn try_from(date: DateTime) -> Result<Self, Self::Error> {
+        let err = || PsruError::InvalidDateTime(date.to_string());
+        if !date.is_valid() {
+            return Err(err());
+        }
+
+        let days = days_from_civil(date.year, date.month, date.day);
+        let int = u32::try_from(days + UNIX_EPOCH).map_err(|_| err())?;
+        let length = LeapSeconds::builtin().day_length(int);
+        if date.second == 60 && length <= 86_400 {
+            return Err(err());
+        }
+
+        utc_mjd(int, date.picoseconds(), length).ok_or_else(err)
+    }
+}
+
+impl Mjd {
+    /// The date as a year with a fraction, which is the part of the days of
+    /// the year that have passed. This is not exact.
+    pub fn decimal_year(&self) -> f64 {
+        let date = DateTime::from(*self);
+        let start = days_from_civil(date.year, 1, 1) + UNIX_EPOCH;
+        let end = days_from_civil(date.year + 1, 1, 1) + UNIX_EPOCH;
+
+        #[allow(clippy::cast_precision_loss)]
+        let days = (f64::from(self.int()) - start as f64) + self.frac();
+        #[allow(clippy::cast_precision_loss)]
+        let part = days / (end - start) as f64;
+        f64::from(date.year) + part
+    }
+
+    /// The MJD of a year with a fraction, see [`Mjd::decimal_year`], to the
+    /// nearest picosecond. Gives `None` if it is before MJD 0, beyond the
+    /// last MJD, or is not a number.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn from_decimal_year(year: f64) -> Option<Self> {
+        let whole = year.floor();
+        if !(f64::from(i32::MIN)..f64::from(i32::MAX)).contains(&whole) {
+            return None;
+        }
+
+        let start = days_from_civil(whole as i32, 1, 1) + UNIX_EPOCH;
+        let end = days_from_civil(whole as i32 + 1, 1, 1) + UNIX_EPOCH;
+        #[allow(clippy::cast_precision_loss)]
+        let days = (year - whole) * (end - start) as f64;
+
+        Self::from_picoseconds(u32::try_from(start).ok()?, 0)?
+            .checked_add(TimeDelta::checked_from_days(days)?)
+    }
+}
+
+/// A Julian Date, which counts days from noon of 1 January 4713 BC. It
+/// holds an [`Mjd`], and can be read and written exactly like one.
+///
+/// ```
+/// # use psrutils::data_types::{Jd, Mjd};
+/// let jd = "2457754.5000000000001".parse::<Jd>().unwrap();
+/// assert_eq!(Mjd::from(jd).to_string(), "57754.0000000000001");
+/// assert_eq!(jd.to_string(), "2457754.5000000000001");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Jd(pub Mjd);
+
+/// Writes the JD in decimal, like [`Mjd`].
+impl std::fmt::Display for Jd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (int, ps) = self.parts();
+        f.pad_integral(true, "", &decimal(int, ps, f.precision()))
+    }
+}
+impl FromStr for Jd {
+    type Err = PsruError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || PsruError::unparsable(s, "JD");
+
+        let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+        let int = int.parse::<u64>().map_err(|_| err())?;
+        let ps = fraction(frac).ok_or_else(err)?;
+
+        let half = PS_PER_DAY / 2;
+        let (days, ps) = if ps >= half {
+            (int.checked_sub(JD_OFFSET), ps - half)
+        } else {
+            (int.checked_sub(JD_OFFSET + 1), ps + half)
+        };
+        let days = days.and_then(|d| u32::try_from(d).ok()).ok_or_else(err)?;
+        let mjd = Mjd::from_picoseconds(days, ps);
+        mjd.map(Self).ok_or_else(err)
+    }
+}
+impl Jd {
+    /// Converts the value into a pure `f64`.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn to_f64(&self) -> f64 {
+        let (int, ps) = self.parts();
+        int as f64 + ps as f64 / PS_PER_DAY as f64
+    }
+
+    /// The whole days and picoseconds into the day.
+    fn parts(&self) -> (u64, u64) {
+        let half = PS_PER_DAY / 2;
+        let (int, ps) = (u64::from(self.0.int()), self.0.picoseconds());
+        if ps >= half {
+            (int + JD_OFFSET + 1, ps - half)
+        } else {
+            (int + JD_OFFSET, ps + half)
+        }
+    }
+}
+impl From<Mjd> for Jd {
+    fn from(mjd: Mjd) -> Self {
+        Self(mjd)
+    }
+}
+impl From<Jd> for Mjd {
+    fn from(jd: Jd) -> Self {
+        jd.0
+    }
+}
+
+/// The picoseconds of UTC into the day of an MJD, for a day of some
+/// seconds. On days with a leap second, each picosecond of the MJD is a bit
+/// longer than one of UTC.
+pub(super) fn utc_picoseconds(mjd: Mjd, length: u64) -> u64 {
+    let length = u128::from(length);
+    let day = u128::from(PS_PER_DAY / PS_PER_SECOND);
+    let ps = (u128::from(mjd.picoseconds()) * length + day / 2) / day;
+    u64::try_from(ps).unwrap_or_default()
+}
+
+/// The MJD of a day and picoseconds of UTC into it, which may be up to the
+/// end of a leap second, the other way around from [`utc_picoseconds`].
+pub(super) fn utc_mjd(int: u32, picoseconds: u64, length: u64) -> Option<Mjd> {
+    let length = u128::from(length);
+    let day = u128::from(PS_PER_DAY / PS_PER_SECOND);
+    let ps = (u128::from(picoseconds) * day + length / 2) / length;
+    Mjd::from_picoseconds(int, ps.try_into().ok()?)
+}
+
+/// The days from 1970-01-01 to a date.
+fn days_from_civil(year: i32, month: u8, day: u8) -> i64 {
+    let year = i64::from(year) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = (i64::from(month) + 9) % 12;
+    let day_of_year = (153 * month + 2) / 5 + i64::from(day) - 1;
+    let day_of_era =
+        year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The date some days after 1970-01-01.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn civil_from_days(days: i64) -> (i32, u8, u8) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+        - day_of_era / 146_096)
+        / 365;
+    let day_of_year =
+        day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year as i32, month as u8, day as u8)
+}
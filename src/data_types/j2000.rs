@@ -40,21 +40,15 @@ impl FromStr for J2000Coord<RACoordType> {
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
         let coord_parts = s.split(':').collect::<Vec<_>>();
         if coord_parts.len() != 3 {
-            return Err(PsruError::InvalidRA(s.to_string()));
+            return Err(PsruError::InvalidRA(None, s.to_string()));
         }
 
         let major = coord_parts[0].parse::<i8>().map_err(|_| {
-            PsruError::Unparsable {
-                value: coord_parts[0].to_string(),
-                to_type: "hours [0, 24)",
-            }
-        })?;
-        let minutes = coord_parts[1].parse::<u8>().map_err(|_| {
-            PsruError::Unparsable {
-                value: coord_parts[0].to_string(),
-                to_type: "minutes",
-            }
+            PsruError::unparsable(coord_parts[0], "hours [0, 24)")
         })?;
+        let minutes = coord_parts[1]
+            .parse::<u8>()
+            .map_err(|_| PsruError::unparsable(coord_parts[0], "minutes"))?;
         let seconds = parse_f64(coord_parts[2])?;
 
         let ra = Self {
@@ -74,21 +68,15 @@ impl FromStr for J2000Coord<DECCoordType> {
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
         let coord_parts = s.split(':').collect::<Vec<_>>();
         if coord_parts.len() != 3 {
-            return Err(PsruError::InvalidRA(s.to_string()));
+            return Err(PsruError::InvalidRA(None, s.to_string()));
         }
 
         let major = coord_parts[0].parse::<i8>().map_err(|_| {
-            PsruError::Unparsable {
-                value: coord_parts[0].to_string(),
-                to_type: "degrees [-90, 90]",
-            }
-        })?;
-        let minutes = coord_parts[1].parse::<u8>().map_err(|_| {
-            PsruError::Unparsable {
-                value: coord_parts[0].to_string(),
-                to_type: "minutes",
-            }
+            PsruError::unparsable(coord_parts[0], "degrees [-90, 90]")
         })?;
+        let minutes = coord_parts[1]
+            .parse::<u8>()
+            .map_err(|_| PsruError::unparsable(coord_parts[0], "minutes"))?;
         let seconds = parse_f64(coord_parts[2])?;
 
         let dec = Self {
@@ -135,7 +123,7 @@ impl J2000Coord<RACoordType> {
             || self.seconds >= 60.0
             || self.seconds < 0.0
         {
-            return Err(PsruError::InvalidRA(self.to_string()));
+            return Err(PsruError::InvalidRA(None, self.to_string()));
         }
 
         Ok(())
@@ -176,7 +164,7 @@ impl J2000Coord<DECCoordType> {
             || self.seconds >= 60.0
             || self.seconds < 0.0
         {
-            return Err(PsruError::InvalidDec(self.to_string()));
+            return Err(PsruError::InvalidDec(None, self.to_string()));
         }
 
         Ok(())
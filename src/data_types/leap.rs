@@ -0,0 +1,296 @@
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use super::mjd::PS_PER_SECOND;
+use super::{DateTime, Mjd, TimeDelta};
+use crate::error::PsruError;
+
+/// The first day of each value of TAI - UTC (s), up to the leap second at
+/// the end of 2016.
+const BUILTIN: [(u32, i64); 28] = [
+    (41_317, 10),
+    (41_499, 11),
+    (41_683, 12),
+    (42_048, 13),
+    (42_413, 14),
+    (42_778, 15),
+    (43_144, 16),
+    (43_509, 17),
+    (43_874, 18),
+    (44_239, 19),
+    (44_786, 20),
+    (45_151, 21),
+    (45_516, 22),
+    (46_247, 23),
+    (47_161, 24),
+    (47_892, 25),
+    (48_257, 26),
+    (48_804, 27),
+    (49_169, 28),
+    (49_534, 29),
+    (50_083, 30),
+    (50_630, 31),
+    (51_179, 32),
+    (53_736, 33),
+    (54_832, 34),
+    (56_109, 35),
+    (57_204, 36),
+    (57_754, 37),
+];
+/// The expiry of the built-in table, 2027-06-28, as given by IERS Bulletin
+/// C 72.
+const BUILTIN_EXPIRES: u32 = 61_584;
+
+const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+/// The history of leap seconds, as TAI - UTC from each day it changed.
+///
+/// A table is only trusted up to its expiry, after which a leap second may
+/// have been added that it does not know of. Asking about later days is an
+/// error, and so is asking about days before the first entry (1972 for
+/// the built-in table).
+///
+/// The built-in table can be replaced by reading the IERS `Leap_Second.dat`
+/// or the Tempo2 `leap.sec`. The latter does not say when it expires, so
+/// the expiry has to be given along with it.
+///
+/// ```
+/// # use psrutils::data_types::{LeapSeconds, Mjd};
+/// let text = "\
+/// ##  File expires on 28 June 2027
+/// ##    MJD        Date        TAI-UTC (s)
+///     57204.0    1  7 2015       36
+///     57754.0    1  1 2017       37
+/// ";
+/// let leaps = LeapSeconds::read(text.as_bytes()).unwrap();
+/// assert_eq!(leaps.expires(), "61584.0".parse::<Mjd>().unwrap());
+///
+/// let day = "57753.5".parse::<Mjd>().unwrap();
+/// assert_eq!(leaps.tai_utc(day).unwrap().as_seconds(), 36.0);
+/// assert_eq!(leaps.day_seconds(day).unwrap(), 86_401);
+/// assert!(leaps.tai_utc("61585.0".parse().unwrap()).is_err());
+///
+/// let tempo2 = "57204 36\n57754 37\n";
+/// assert!(LeapSeconds::read(tempo2.as_bytes()).is_err());
+/// let expires = "61400.0".parse::<Mjd>().unwrap();
+/// let leaps = LeapSeconds::read_with_expiry(tempo2.as_bytes(), expires);
+/// assert_eq!(leaps.unwrap().expires(), expires);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeapSeconds {
+    /// The first day of each value of TAI - UTC (s), in order.
+    changes: Cow<'static, [(u32, i64)]>,
+    /// The last day the table is known to be right for.
+    expires: u32,
+}
+impl Default for LeapSeconds {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+impl LeapSeconds {
+    /// The table compiled into the crate, which expires on 2027-06-28.
+    pub const fn builtin() -> Self {
+        Self {
+            changes: Cow::Borrowed(&BUILTIN),
+            expires: BUILTIN_EXPIRES,
+        }
+    }
+
+    /// Reads a leap second table, either the IERS `Leap_Second.dat`, with
+    /// lines of MJD, day, month, year and TAI - UTC, or the Tempo2
+    /// `leap.sec`, with lines of only the MJD and TAI - UTC. Lines starting
+    /// with `#` are comments.
+    ///
+    /// The expiry is taken from a comment like `File expires on 28 June
+    /// 2027`. Tables without one, like `leap.sec`, are read with
+    /// [`LeapSeconds::read_with_expiry`].
+    ///
+    /// # Errors
+    /// Fails if a line can not be read, if the days are not in order, or
+    /// with [`PsruError::LeapSecondsNoExpiry`] if the expiry is not stated.
+    pub fn read(reader: impl BufRead) -> Result<Self, PsruError> {
+        let (changes, expires) = Self::parse(reader)?;
+        Ok(Self {
+            changes: Cow::Owned(changes),
+            expires: expires.ok_or(PsruError::LeapSecondsNoExpiry)?,
+        })
+    }
+
+    /// Reads a leap second table like [`LeapSeconds::read`], but with the
+    /// expiry given, instead of any stated in the table. It should be the
+    /// expiry of the IERS Bulletin C the table was made from.
+    ///
+    /// # Errors
+    /// Fails like [`LeapSeconds::read`], except for the expiry.
+    pub fn read_with_expiry(
+        reader: impl BufRead,
+        expires: Mjd,
+    ) -> Result<Self, PsruError> {
+        let (changes, _) = Self::parse(reader)?;
+        Ok(Self {
+            changes: Cow::Owned(changes),
+            expires: expires.int(),
+        })
+    }
+
+    /// The changes in a table, and its expiry if it states one.
+    #[allow(clippy::type_complexity)]
+    fn parse(
+        reader: impl BufRead,
+    ) -> Result<(Vec<(u32, i64)>, Option<u32>), PsruError> {
+        let mut changes = Vec::<(u32, i64)>::new();
+        let mut expires = None;
+
+        for line in reader.lines() {
+            let line = line?;
+            let err = || PsruError::unparsable(&line, "leap second");
+
+            if let Some(comment) = line.trim_start().strip_prefix('#') {
+                if let Some((_, date)) = comment.split_once("expires on") {
+                    expires = Some(expiry(date.trim()).ok_or_else(err)?);
+                }
+                continue;
+            }
+
+            let parts = line.split_whitespace().collect::<Vec<_>>();
+            let (day, offset) = match parts[..] {
+                [] => continue,
+                [day, offset] | [day, _, _, _, offset] => (day, offset),
+                _ => return Err(err()),
+            };
+            let day = day.parse::<Mjd>().map_err(|_| err())?;
+            let offset = offset.parse::<i64>().map_err(|_| err())?;
+            if day.picoseconds() != 0
+                || changes.last().is_some_and(|(last, _)| *last >= day.int())
+            {
+                return Err(err());
+            }
+
+            changes.push((day.int(), offset));
+        }
+
+        Ok((changes, expires))
+    }
+
+    /// Reads a leap second file from the filesystem, like
+    /// [`LeapSeconds::read`], which needs it to state its expiry.
+    ///
+    /// # Errors
+    /// Will throw errors for bad files or contents.
+    pub fn read_path(path: &Path) -> Result<Self, PsruError> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    /// The same table with another expiry, e.g. that of a newer IERS
+    /// Bulletin C which did not add a leap second.
+    #[must_use]
+    pub fn with_expiry(self, expires: Mjd) -> Self {
+        Self {
+            expires: expires.int(),
+            ..self
+        }
+    }
+
+    /// The last day the table is known to be right for.
+    pub fn expires(&self) -> Mjd {
+        Mjd::from_picoseconds(self.expires, 0).unwrap_or_else(|| {
+            unreachable!("The start of a day is in range");
+        })
+    }
+
+    /// The days TAI - UTC changed, and its values (s) from then.
+    pub fn changes(&self) -> &[(u32, i64)] {
+        &self.changes
+    }
+
+    /// Whether the table is known to be right for the day of an MJD.
+    pub fn covers(&self, mjd: Mjd) -> bool {
+        let day = mjd.int();
+        self.changes.first().is_some_and(|(first, _)| *first <= day)
+            && day <= self.expires
+    }
+
+    /// TAI - UTC on the day of an MJD.
+    ///
+    /// # Errors
+    /// Fails with [`PsruError::LeapSecondsExpired`] after the expiry, and
+    /// with [`PsruError::TimeOutOfRange`] before the first entry.
+    pub fn tai_utc(&self, mjd: Mjd) -> Result<TimeDelta, PsruError> {
+        self.check(mjd)?;
+        let seconds = self.offset(mjd.int()).unwrap_or_default();
+        Ok(TimeDelta::from_picoseconds(
+            i128::from(seconds) * i128::from(PS_PER_SECOND),
+        ))
+    }
+
+    /// The length in seconds of the UTC day of an MJD, which is 86401 for
+    /// days that end with a leap second.
+    ///
+    /// # Errors
+    /// Fails like [`LeapSeconds::tai_utc`].
+    pub fn day_seconds(&self, mjd: Mjd) -> Result<u64, PsruError> {
+        self.check(mjd)?;
+        Ok(self.day_length(mjd.int()))
+    }
+
+    /// The length of a day, taking days outside of the table to have no
+    /// leap seconds.
+    pub(super) fn day_length(&self, day: u32) -> u64 {
+        let change = self
+            .offset(day)
+            .zip(self.offset(day.saturating_add(1)))
+            .map_or(0, |(today, tomorrow)| tomorrow - today);
+        86_400_u64.saturating_add_signed(change)
+    }
+
+    /// TAI - UTC (s) on a day, if it is after the first entry.
+    fn offset(&self, day: u32) -> Option<i64> {
+        let index = self.changes.partition_point(|(start, _)| *start <= day);
+        index.checked_sub(1).map(|i| self.changes[i].1)
+    }
+
+    /// Fails if the table is not known to be right for the day of an MJD.
+    fn check(&self, mjd: Mjd) -> Result<(), PsruError> {
+        let expires = self.expires();
+        if self.covers(mjd) {
+            Ok(())
+        } else if mjd > expires {
+            Err(PsruError::LeapSecondsExpired(mjd, expires))
+        } else {
+            Err(PsruError::TimeOutOfRange(mjd.to_string()))
+        }
+    }
+}
+
+/// The MJD of a date like `28 June 2026`.
+fn expiry(date: &str) -> Option<u32> {
+    let [day, month, year] = date.split_whitespace().collect::<Vec<_>>()[..]
+    else {
+        return None;
+    };
+
+    let month = month.to_ascii_lowercase();
+    let month = MONTHS.iter().position(|m| *m == month)?;
+    let date = DateTime::date(
+        year.parse().ok()?,
+        u8::try_from(month + 1).ok()?,
+        day.parse().ok()?,
+    );
+    Mjd::try_from(date).ok().map(|mjd| mjd.int())
+}
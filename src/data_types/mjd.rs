@@ -1,68 +1,357 @@
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
 use std::str::FromStr;
 
 use crate::error::PsruError;
 
+/// Picoseconds in a day.
+pub(super) const PS_PER_DAY: u64 = 86_400 * PS_PER_SECOND;
+/// Picoseconds in a second.
+pub(super) const PS_PER_SECOND: u64 = 1_000_000_000_000;
+/// Fractional digits beyond this many are too small to matter when parsing.
+const MAX_DIGITS: u32 = 20;
+/// Enough fractional digits to tell any two MJDs apart.
+const ROUND_TRIP_DIGITS: usize = 17;
+
 /// Represents a date-time in MJD.
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+///
+/// The fraction of the day is kept as a whole number of picoseconds, so that
+/// moving by any number of nanoseconds is exact, and up to 16 fractional
+/// digits are read and written back unchanged.
+///
+/// ```
+/// # use psrutils::data_types::{Mjd, TimeDelta};
+/// let mjd = "55000.1234567890123456".parse::<Mjd>().unwrap();
+/// assert_eq!(mjd.to_string(), "55000.1234567890123456");
+/// assert_eq!(format!("{mjd:.4}"), "55000.1235");
+///
+/// let later = mjd + TimeDelta::from_nanos(1);
+/// assert_eq!((later - mjd).picoseconds(), 1000);
+/// assert!(later > mjd);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Mjd {
     int: u32,
-    frac: f64,
+    /// Always less than a day.
+    ps: u64,
 }
 
+/// Writes the MJD in decimal. With a precision, that many fractional digits
+/// are written (rounded), and otherwise as few as are needed to read back
+/// the same value, but at least one.
 impl std::fmt::Display for Mjd {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "MJD {}.{}", self.int, &self.frac.to_string()[1..])
+        let text = decimal(u64::from(self.int), self.ps, f.precision());
+        f.pad_integral(true, "", &text)
     }
 }
 impl FromStr for Mjd {
     type Err = PsruError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let err = |s: &str| PsruError::Unparsable {
-            value: s.to_string(),
-            to_type: "MJD",
-        };
-
-        let parts = s.split('.').collect::<Vec<_>>();
-        if parts.len() == 1 {
-            Ok(Self {
-                int: parts[0].parse().map_err(|_| err(s))?,
-                frac: 0.0,
-            })
-        } else if parts.len() == 2 {
-            Ok(Self {
-                int: parts[0].parse().map_err(|_| err(s))?,
-                frac: (String::from("0.") + parts[1])
-                    .parse()
-                    .map_err(|_| err(s))?,
-            })
-        } else {
-            Err(err(s))
-        }
+        let err = || PsruError::unparsable(s, "MJD");
+
+        let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+        let int = int.parse::<u32>().map_err(|_| err())?;
+        let ps = fraction(frac).ok_or_else(err)?;
+
+        Self::from_picoseconds(int, ps).ok_or_else(err)
     }
 }
 impl Mjd {
+    /// The fraction is taken to be the shortest decimal that it is the
+    /// closest `f64` to, e.g. `0.1` is exactly a tenth of a day.
+    ///
     /// # Panics
     /// `panic`s if the fractional part is outside of the range [0, 1).
     pub fn new(int: u32, frac: f64) -> Self {
         assert!(frac < 1.0);
         assert!(frac >= 0.0);
 
-        Self { int, frac }
+        let text = frac.to_string();
+        let digits = text.strip_prefix("0.").unwrap_or_default();
+        format!("{int}.{digits}").parse().expect("MJD out of range")
+    }
+
+    /// An MJD from the integer part and picoseconds into the day. Whole days
+    /// of picoseconds are carried over to the integer part.
+    ///
+    /// Gives `None` if the integer part overflows.
+    pub fn from_picoseconds(int: u32, picoseconds: u64) -> Option<Self> {
+        let days = u32::try_from(picoseconds / PS_PER_DAY).ok()?;
+        Some(Self {
+            int: int.checked_add(days)?,
+            ps: picoseconds % PS_PER_DAY,
+        })
     }
 
     /// Converts the value into a pure `f64`.
-    pub const fn to_f64(&self) -> f64 {
-        self.int as f64 + self.frac
+    #[allow(clippy::cast_precision_loss)]
+    pub fn to_f64(&self) -> f64 {
+        f64::from(self.int) + self.frac()
     }
 
     /// The integer part.
-    pub fn int(&self) -> u32 {
+    pub const fn int(&self) -> u32 {
         self.int
     }
 
     /// The fractional part, always in the range [0, 1).
+    #[allow(clippy::cast_precision_loss)]
     pub fn frac(&self) -> f64 {
-        self.frac
+        let frac = self.ps as f64 / PS_PER_DAY as f64;
+        frac.min(1.0 - f64::EPSILON / 2.0)
+    }
+
+    /// The picoseconds into the day.
+    pub const fn picoseconds(&self) -> u64 {
+        self.ps
+    }
+
+    /// Moves the date-time by a time interval, or gives `None` if that ends
+    /// up before MJD 0 or after the last representable day.
+    pub fn checked_add(self, delta: TimeDelta) -> Option<Self> {
+        let total = self.total().checked_add(delta.0)?;
+        let total = u128::try_from(total).ok()?;
+        let int = u32::try_from(total / u128::from(PS_PER_DAY)).ok()?;
+
+        #[allow(clippy::cast_possible_truncation)]
+        let ps = (total % u128::from(PS_PER_DAY)) as u64;
+        Some(Self { int, ps })
+    }
+
+    /// Moves the date-time back by a time interval, like
+    /// [`Mjd::checked_add`].
+    pub fn checked_sub(self, delta: TimeDelta) -> Option<Self> {
+        self.checked_add(-delta)
+    }
+
+    /// Picoseconds since MJD 0.
+    fn total(self) -> i128 {
+        i128::from(self.int) * i128::from(PS_PER_DAY) + i128::from(self.ps)
+    }
+}
+impl Add<TimeDelta> for Mjd {
+    type Output = Self;
+
+    /// # Panics
+    /// `panic`s if the result is out of range, see [`Mjd::checked_add`].
+    fn add(self, rhs: TimeDelta) -> Self {
+        self.checked_add(rhs).expect("MJD out of range")
+    }
+}
+impl Sub<TimeDelta> for Mjd {
+    type Output = Self;
+
+    /// # Panics
+    /// `panic`s if the result is out of range, see [`Mjd::checked_sub`].
+    fn sub(self, rhs: TimeDelta) -> Self {
+        self.checked_sub(rhs).expect("MJD out of range")
+    }
+}
+impl AddAssign<TimeDelta> for Mjd {
+    /// # Panics
+    /// `panic`s if the result is out of range, see [`Mjd::checked_add`].
+    fn add_assign(&mut self, rhs: TimeDelta) {
+        *self = *self + rhs;
+    }
+}
+impl SubAssign<TimeDelta> for Mjd {
+    /// # Panics
+    /// `panic`s if the result is out of range, see [`Mjd::checked_sub`].
+    fn sub_assign(&mut self, rhs: TimeDelta) {
+        *self = *self - rhs;
+    }
+}
+impl Sub for Mjd {
+    type Output = TimeDelta;
+
+    fn sub(self, rhs: Self) -> TimeDelta {
+        TimeDelta(self.total() - rhs.total())
+    }
+}
+
+/// A signed time interval, kept as a whole number of picoseconds, e.g. the
+/// difference between two [`Mjd`]s.
+///
+/// ```
+/// # use psrutils::data_types::TimeDelta;
+/// let delta = TimeDelta::from_seconds(1.5) - TimeDelta::from_nanos(2);
+/// assert_eq!(delta.picoseconds(), 1_499_999_998_000);
+/// assert_eq!(delta.to_string(), "1.499999998 s");
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TimeDelta(i128);
+impl TimeDelta {
+    /// No time at all.
+    pub const ZERO: Self = Self(0);
+
+    /// An interval of a whole number of picoseconds.
+    pub const fn from_picoseconds(picoseconds: i128) -> Self {
+        Self(picoseconds)
+    }
+
+    /// An interval of a whole number of nanoseconds.
+    #[allow(clippy::cast_lossless)]
+    pub const fn from_nanos(nanoseconds: i64) -> Self {
+        Self(nanoseconds as i128 * 1000)
+    }
+
+    /// An interval of a number of seconds, rounded to the nearest
+    /// picosecond.
+    ///
+    /// # Panics
+    /// `panic`s if the number is not finite, or too large to be kept in
+    /// picoseconds (beyond about 1.7e26 s), see
+    /// [`TimeDelta::checked_from_seconds`].
+    pub fn from_seconds(seconds: f64) -> Self {
+        Self::checked_from_seconds(seconds).expect("interval out of range")
+    }
+
+    /// An interval of a number of seconds, rounded to the nearest
+    /// picosecond, or `None` if the number is not finite or too large.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    pub fn checked_from_seconds(seconds: f64) -> Option<Self> {
+        // Whole and fractional seconds apart, to keep the precision
+        let whole = seconds.trunc();
+        let limit = (i128::MAX / i128::from(PS_PER_SECOND)) as f64;
+        if !seconds.is_finite() || whole.abs() >= limit {
+            return None;
+        }
+
+        let ps = ((seconds - whole) * 1e12).round();
+        (whole as i128)
+            .checked_mul(i128::from(PS_PER_SECOND))?
+            .checked_add(ps as i128)
+            .map(Self)
+    }
+
+    /// An interval of a number of days, rounded to the nearest picosecond.
+    ///
+    /// # Panics
+    /// `panic`s like [`TimeDelta::from_seconds`].
+    pub fn from_days(days: f64) -> Self {
+        Self::from_seconds(days * 86_400.0)
+    }
+
+    /// An interval of a number of days, rounded to the nearest picosecond,
+    /// or `None` if the number is not finite or too large.
+    pub fn checked_from_days(days: f64) -> Option<Self> {
+        Self::checked_from_seconds(days * 86_400.0)
+    }
+
+    /// The whole number of picoseconds.
+    pub const fn picoseconds(&self) -> i128 {
+        self.0
+    }
+
+    /// The interval in seconds, as an `f64`.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn as_seconds(&self) -> f64 {
+        let whole = self.0 / i128::from(PS_PER_SECOND);
+        let part = self.0 % i128::from(PS_PER_SECOND);
+        (part as f64).mul_add(1e-12, whole as f64)
+    }
+
+    /// The interval in days, as an `f64`.
+    pub fn as_days(&self) -> f64 {
+        self.as_seconds() / 86_400.0
+    }
+}
+impl Add for TimeDelta {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0 + rhs.0)
+    }
+}
+impl Sub for TimeDelta {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0 - rhs.0)
+    }
+}
+impl Neg for TimeDelta {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(-self.0)
+    }
+}
+/// Writes the interval in seconds, with all the digits needed.
+impl std::fmt::Display for TimeDelta {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let ps = self.0.unsigned_abs();
+        let whole = ps / u128::from(PS_PER_SECOND);
+        let part = ps % u128::from(PS_PER_SECOND);
+
+        if part == 0 {
+            write!(f, "{sign}{whole} s")
+        } else {
+            let part = format!("{part:012}");
+            write!(f, "{sign}{whole}.{} s", part.trim_end_matches('0'))
+        }
+    }
+}
+
+/// Reads the digits after the decimal point as picoseconds of a day, rounded
+/// to nearest. That can be a whole day.
+pub(super) fn fraction(digits: &str) -> Option<u64> {
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let digits = digits.get(..MAX_DIGITS as usize).unwrap_or(digits);
+    let scale = 10_u128.pow(digits.len().try_into().unwrap_or(MAX_DIGITS));
+    let numerator = digits.parse::<u128>().unwrap_or_default();
+    let ps = (numerator * u128::from(PS_PER_DAY) + scale / 2) / scale;
+    ps.try_into().ok()
+}
+
+/// Writes a number of days in decimal, from the whole days and picoseconds.
+/// With a precision, that many fractional digits are written (rounded), and
+/// otherwise as few as are needed to read back the same value, but at least
+/// one.
+pub(super) fn decimal(int: u64, ps: u64, precision: Option<usize>) -> String {
+    precision.map_or_else(
+        || {
+            (1..ROUND_TRIP_DIGITS)
+                .map(|precision| fixed(int, ps, precision))
+                .find(|text| {
+                    let (_, digits) = text.split_once('.').unwrap_or_default();
+                    fraction(digits) == Some(ps)
+                })
+                .unwrap_or_else(|| fixed(int, ps, ROUND_TRIP_DIGITS))
+        },
+        |precision| fixed(int, ps, precision),
+    )
+}
+
+/// Days with a fixed number of fractional digits, rounded to nearest.
+fn fixed(mut int: u64, ps: u64, precision: usize) -> String {
+    let mut digits = Vec::with_capacity(precision);
+    let mut remainder = ps;
+    for _ in 0..precision {
+        remainder *= 10;
+        digits.push(remainder / PS_PER_DAY);
+        remainder %= PS_PER_DAY;
+    }
+
+    if remainder * 2 >= PS_PER_DAY {
+        let carry = digits.iter_mut().rev().all(|digit| {
+            *digit = (*digit + 1) % 10;
+            *digit == 0
+        });
+        if carry {
+            int += 1;
+        }
+    }
+
+    let digits = digits.iter().map(u64::to_string).collect::<String>();
+    if digits.is_empty() {
+        int.to_string()
+    } else {
+        format!("{int}.{digits}")
     }
 }
@@ -0,0 +1,393 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+
+use super::calendar::{utc_mjd, utc_picoseconds};
+use super::{LeapSeconds, Mjd, TimeDelta};
+use crate::error::PsruError;
+
+/// TT - TAI.
+const TT_TAI: TimeDelta = TimeDelta::from_picoseconds(32_184_000_000_000);
+/// How much slower TT runs than TCG.
+const L_G: f64 = 6.969_290_134e-10;
+/// How much slower TDB runs than TCB.
+const L_B: f64 = 1.550_519_768e-8;
+/// TDB - TCB at [`t0`].
+const TDB0: TimeDelta = TimeDelta::from_picoseconds(-65_500_000);
+/// Days in a Julian millennium.
+const MILLENNIUM: f64 = 365_250.0;
+
+/// The leading terms of the FB90 series for TDB - TT, as the amplitude (s),
+/// frequency (rad per Julian millennium) and phase (rad), for each power of
+/// the time since J2000.
+const FB90: [&[(f64, f64, f64)]; 3] = [
+    &[
+        (1_656.674_564e-6, 6_283.075_849_991, 6.240_054_195),
+        (22.417_471e-6, 5_753.384_884_897, 4.296_977_442),
+        (13.839_792e-6, 12_566.151_699_983, 6.196_904_410),
+        (4.770_086e-6, 529.690_965_095, 0.444_401_603),
+        (4.676_740e-6, 6_069.776_754_553, 4.021_195_093),
+        (2.256_707e-6, 213.299_095_438, 5.543_113_262),
+        (1.694_205e-6, -3.523_118_349, 5.025_132_748),
+        (1.554_905e-6, 77_713.771_467_920, 5.198_467_090),
+        (1.276_839e-6, 7_860.419_392_439, 5.988_822_341),
+        (1.193_379e-6, 5_223.693_919_802, 3.649_823_730),
+        (1.115_322e-6, 3_930.209_696_220, 1.422_745_069),
+        (0.794_185e-6, 11_506.769_769_794, 2.322_313_077),
+        (0.600_309e-6, 1_577.343_542_448, 2.678_271_909),
+        (0.496_817e-6, 6_208.294_251_424, 5.696_701_824),
+        (0.486_306e-6, 5_884.926_846_583, 0.520_007_179),
+        (0.468_597e-6, 6_244.942_814_354, 5.866_398_759),
+        (0.447_061e-6, 26.298_319_800, 3.615_796_498),
+        (0.435_206e-6, -398.149_003_408, 4.349_338_347),
+        (0.432_392e-6, 74.781_598_567, 2.435_898_309),
+        (0.375_510e-6, 5_507.553_238_667, 4.103_476_804),
+    ],
+    &[
+        (102.156_724e-6, 6_283.075_849_991, 4.249_032_005),
+        (1.706_807e-6, 12_566.151_699_983, 4.205_904_248),
+    ],
+    &[(4.322_990e-6, 6_283.075_849_991, 2.642_893_748)],
+];
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// What conversions between scales need.
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy)]
+pub struct Context<'a> {
+    leaps: &'a LeapSeconds,
+}
+
+/// A time scale that [`Epoch`]s are in, which is one of [`Utc`], [`Tai`],
+/// [`Tt`], [`Tcg`], [`Tdb`] and [`Tcb`].
+pub trait TimeScale:
+    Debug + Clone + Copy + PartialEq + Eq + PartialOrd + Ord + Hash + sealed::Sealed
+{
+    /// The usual abbreviation, e.g. `"TDB"`.
+    const NAME: &'static str;
+
+    /// The same instant in TT.
+    #[doc(hidden)]
+    fn to_tt(mjd: Mjd, ctx: Context) -> Result<Mjd, PsruError>;
+
+    /// The same instant as one in TT.
+    #[doc(hidden)]
+    fn from_tt(tt: Mjd, ctx: Context) -> Result<Mjd, PsruError>;
+}
+
+/// Coordinated Universal Time, which is kept within a second of the
+/// rotation of the Earth by leap seconds. MJDs on days with a leap second
+/// count 86401 seconds to that day.
+///
+/// It can only be converted from 1972, when leap seconds started, up to the
+/// expiry of the [`LeapSeconds`] used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Utc;
+/// International Atomic Time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Tai;
+/// Terrestrial Time, which is TAI + 32.184 s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Tt;
+/// Geocentric Coordinate Time, which runs a bit faster than TT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Tcg;
+/// Barycentric Dynamical Time, which stays within a couple of milliseconds
+/// of TT. The units of `UNITS TDB` in `.par` files.
+///
+/// Only the largest terms of the FB90 series for TDB - TT are used, which is
+/// good to some microseconds, not the nanoseconds of the full series. The
+/// time ephemeris of a `.par` file is not taken into account, as FB90 and
+/// IF99 differ by far less than that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Tdb;
+/// Barycentric Coordinate Time, which runs faster than TDB. The units of
+/// `UNITS TCB` in `.par` files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Tcb;
+
+impl sealed::Sealed for Utc {}
+impl sealed::Sealed for Tai {}
+impl sealed::Sealed for Tt {}
+impl sealed::Sealed for Tcg {}
+impl sealed::Sealed for Tdb {}
+impl sealed::Sealed for Tcb {}
+
+impl TimeScale for Utc {
+    const NAME: &'static str = "UTC";
+
+    fn to_tt(mjd: Mjd, ctx: Context) -> Result<Mjd, PsruError> {
+        let length = ctx.leaps.day_seconds(mjd)?;
+        let offset = ctx.leaps.tai_utc(mjd)?;
+        let into =
+            TimeDelta::from_picoseconds(utc_picoseconds(mjd, length).into());
+
+        let tai = Mjd::from_picoseconds(mjd.int(), 0)
+            .and_then(|start| start.checked_add(offset + into))
+            .ok_or_else(|| out_of_range(mjd))?;
+        Tai::to_tt(tai, ctx)
+    }
+
+    fn from_tt(tt: Mjd, ctx: Context) -> Result<Mjd, PsruError> {
+        let tai = Tai::from_tt(tt, ctx)?;
+        let start = |day| {
+            let start = Mjd::from_picoseconds(day, 0)
+                .ok_or_else(|| out_of_range(tai))?;
+            let offset = ctx.leaps.tai_utc(start)?;
+            start.checked_add(offset).ok_or_else(|| out_of_range(tai))
+        };
+
+        // The UTC day starts later than the TAI one
+        let mut day = tai.int();
+        let mut begin = start(day)?;
+        if tai < begin {
+            day = day.checked_sub(1).ok_or_else(|| out_of_range(tai))?;
+            begin = start(day)?;
+        }
+
+        let midnight =
+            Mjd::from_picoseconds(day, 0).ok_or_else(|| out_of_range(tai))?;
+        let length = ctx.leaps.day_seconds(midnight)?;
+        u64::try_from((tai - begin).picoseconds())
+            .ok()
+            .and_then(|into| utc_mjd(day, into, length))
+            .ok_or_else(|| out_of_range(tai))
+    }
+}
+impl TimeScale for Tai {
+    const NAME: &'static str = "TAI";
+
+    fn to_tt(mjd: Mjd, _: Context) -> Result<Mjd, PsruError> {
+        shift(mjd, TT_TAI)
+    }
+
+    fn from_tt(tt: Mjd, _: Context) -> Result<Mjd, PsruError> {
+        shift(tt, -TT_TAI)
+    }
+}
+impl TimeScale for Tt {
+    const NAME: &'static str = "TT";
+
+    fn to_tt(mjd: Mjd, _: Context) -> Result<Mjd, PsruError> {
+        Ok(mjd)
+    }
+
+    fn from_tt(tt: Mjd, _: Context) -> Result<Mjd, PsruError> {
+        Ok(tt)
+    }
+}
+impl TimeScale for Tcg {
+    const NAME: &'static str = "TCG";
+
+    fn to_tt(mjd: Mjd, _: Context) -> Result<Mjd, PsruError> {
+        shift(mjd, -rate(mjd - t0(), L_G))
+    }
+
+    fn from_tt(tt: Mjd, _: Context) -> Result<Mjd, PsruError> {
+        shift(tt, rate(tt - t0(), L_G / (1.0 - L_G)))
+    }
+}
+impl TimeScale for Tdb {
+    const NAME: &'static str = "TDB";
+
+    fn to_tt(mjd: Mjd, _: Context) -> Result<Mjd, PsruError> {
+        // The series is in TT, so go back twice to get close enough
+        let guess = shift(mjd, -tdb_tt(mjd))?;
+        shift(mjd, -tdb_tt(guess))
+    }
+
+    fn from_tt(tt: Mjd, _: Context) -> Result<Mjd, PsruError> {
+        shift(tt, tdb_tt(tt))
+    }
+}
+impl TimeScale for Tcb {
+    const NAME: &'static str = "TCB";
+
+    fn to_tt(mjd: Mjd, ctx: Context) -> Result<Mjd, PsruError> {
+        let tdb = shift(mjd, -rate(mjd - t0(), L_B))?;
+        Tdb::to_tt(shift(tdb, TDB0)?, ctx)
+    }
+
+    fn from_tt(tt: Mjd, ctx: Context) -> Result<Mjd, PsruError> {
+        let tdb = shift(Tdb::from_tt(tt, ctx)?, -TDB0)?;
+        shift(tdb, rate(tdb - t0(), L_B / (1.0 - L_B)))
+    }
+}
+
+/// An instant as an [`Mjd`] in a time scale.
+///
+/// Instants in different scales can not be mixed up: only instants in the
+/// same scale can be compared or subtracted, and others must be converted
+/// first.
+///
+/// Conversions to and from [`Tdb`] and [`Tcb`] are only good to some
+/// microseconds, see [`Tdb`].
+///
+/// ```
+/// # use psrutils::data_types::{Epoch, Mjd, Tai, TimeDelta, Tt, Utc};
+/// let utc = Epoch::<Utc>::new("57754.0".parse::<Mjd>().unwrap());
+/// let tt = utc.to::<Tt>().unwrap();
+/// assert_eq!(tt.mjd() - utc.mjd(), TimeDelta::from_seconds(69.184));
+///
+/// // One second earlier is in the leap second
+/// let before = "57753.99998842605988356".parse::<Mjd>().unwrap();
+/// let before = Epoch::<Utc>::new(before).to::<Tai>().unwrap();
+/// assert_eq!((utc.to::<Tai>().unwrap() - before).as_seconds(), 1.0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Epoch<S> {
+    mjd: Mjd,
+    scale: PhantomData<S>,
+}
+
+/// Writes the MJD like [`Mjd`] does, followed by the name of the scale.
+impl<S: TimeScale> std::fmt::Display for Epoch<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.mjd, f)?;
+        write!(f, " {}", S::NAME)
+    }
+}
+impl<S: TimeScale> Epoch<S> {
+    /// The instant of an MJD in this scale.
+    pub const fn new(mjd: Mjd) -> Self {
+        Self {
+            mjd,
+            scale: PhantomData,
+        }
+    }
+
+    /// The MJD in this scale.
+    pub const fn mjd(&self) -> Mjd {
+        self.mjd
+    }
+
+    /// The same instant in another scale, with the built-in
+    /// [`LeapSeconds`].
+    ///
+    /// # Errors
+    /// Fails with [`PsruError::TimeOutOfRange`] if the instant can not be
+    /// converted, e.g. UTC before 1972, or the result is before MJD 0, with
+    /// [`PsruError::LeapSecondsExpired`] for UTC after the leap seconds
+    /// expire.
+    pub fn to<T: TimeScale>(self) -> Result<Epoch<T>, PsruError> {
+        self.to_using(&LeapSeconds::builtin())
+    }
+
+    /// The same instant in another scale, like [`Epoch::to`], but with
+    /// other leap seconds, e.g. a newer table read from a file.
+    ///
+    /// # Errors
+    /// Fails like [`Epoch::to`].
+    pub fn to_using<T: TimeScale>(
+        self,
+        leaps: &LeapSeconds,
+    ) -> Result<Epoch<T>, PsruError> {
+        let ctx = Context { leaps };
+        S::to_tt(self.mjd, ctx)
+            .and_then(|tt| T::from_tt(tt, ctx))
+            .map(Epoch::new)
+            .map_err(|err| match err {
+                PsruError::TimeOutOfRange(_) => {
+                    PsruError::TimeOutOfRange(self.to_string())
+                }
+                err => err,
+            })
+    }
+
+    /// Moves the instant by a time interval in this scale, see
+    /// [`Mjd::checked_add`].
+    pub fn checked_add(self, delta: TimeDelta) -> Option<Self> {
+        self.mjd.checked_add(delta).map(Self::new)
+    }
+
+    /// Moves the instant back by a time interval in this scale, see
+    /// [`Mjd::checked_sub`].
+    pub fn checked_sub(self, delta: TimeDelta) -> Option<Self> {
+        self.mjd.checked_sub(delta).map(Self::new)
+    }
+}
+impl<S: TimeScale> Add<TimeDelta> for Epoch<S> {
+    type Output = Self;
+
+    /// # Panics
+    /// `panic`s if the result is out of range, see [`Mjd::checked_add`].
+    fn add(self, rhs: TimeDelta) -> Self {
+        Self::new(self.mjd + rhs)
+    }
+}
+impl<S: TimeScale> Sub<TimeDelta> for Epoch<S> {
+    type Output = Self;
+
+    /// # Panics
+    /// `panic`s if the result is out of range, see [`Mjd::checked_sub`].
+    fn sub(self, rhs: TimeDelta) -> Self {
+        Self::new(self.mjd - rhs)
+    }
+}
+impl<S: TimeScale> AddAssign<TimeDelta> for Epoch<S> {
+    /// # Panics
+    /// `panic`s if the result is out of range, see [`Mjd::checked_add`].
+    fn add_assign(&mut self, rhs: TimeDelta) {
+        *self = *self + rhs;
+    }
+}
+impl<S: TimeScale> SubAssign<TimeDelta> for Epoch<S> {
+    /// # Panics
+    /// `panic`s if the result is out of range, see [`Mjd::checked_sub`].
+    fn sub_assign(&mut self, rhs: TimeDelta) {
+        *self = *self - rhs;
+    }
+}
+impl<S: TimeScale> Sub for Epoch<S> {
+    type Output = TimeDelta;
+
+    fn sub(self, rhs: Self) -> TimeDelta {
+        self.mjd - rhs.mjd
+    }
+}
+
+/// 1977-01-01 00:00:00 TAI, as TT, where TT, TCG and TCB agree (TDB only
+/// up to [`TDB0`]).
+fn t0() -> Mjd {
+    Mjd::from_picoseconds(43_144, 32_184_000_000_000).unwrap_or_else(|| {
+        unreachable!("T0 is in range");
+    })
+}
+
+/// Moves an MJD, or fails if it goes out of range.
+fn shift(mjd: Mjd, delta: TimeDelta) -> Result<Mjd, PsruError> {
+    mjd.checked_add(delta).ok_or_else(|| out_of_range(mjd))
+}
+
+/// The error for an MJD that can not be converted. [`Epoch::to_using`]
+/// replaces the MJD with the one it started from.
+fn out_of_range(mjd: Mjd) -> PsruError {
+    PsruError::TimeOutOfRange(mjd.to_string())
+}
+
+/// The part of an interval, at some rate.
+fn rate(delta: TimeDelta, rate: f64) -> TimeDelta {
+    TimeDelta::from_seconds(delta.as_seconds() * rate)
+}
+
+/// TDB - TT at an instant in TT, see [`Tdb`].
+fn tdb_tt(tt: Mjd) -> TimeDelta {
+    let j2000 = Mjd::from_picoseconds(51_544, 43_200_000_000_000_000);
+    let t = j2000.map_or(0.0, |j2000| (tt - j2000).as_days() / MILLENNIUM);
+
+    let seconds = FB90.iter().rev().fold(0.0, |sum: f64, terms| {
+        let sum_terms = terms
+            .iter()
+            .map(|(amplitude, frequency, phase)| {
+                amplitude * frequency.mul_add(t, *phase).sin()
+            })
+            .sum::<f64>();
+        sum.mul_add(t, sum_terms)
+    });
+
+    TimeDelta::from_seconds(seconds)
+}
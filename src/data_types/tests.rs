@@ -70,3 +70,318 @@ fn mjd() {
     let mjd = "560664".parse::<Mjd>().unwrap();
     assert_eq!(mjd, Mjd::new(560664, 0.0), "Int failed");
 }
+
+#[test]
+fn mjd_text() {
+    for text in [
+        "55000.0",
+        "55000.5",
+        "55000.1234567890123",
+        "55000.0000000000001",
+        "59999.9999999999999999",
+        "0.0000000000000001",
+    ] {
+        let mjd = text.parse::<Mjd>().unwrap();
+        assert_eq!(mjd.to_string(), text);
+    }
+
+    let mjd = "55000.99999999999999".parse::<Mjd>().unwrap();
+    assert_eq!(format!("{mjd:.13}"), "55001.0000000000000");
+    assert_eq!(format!("{mjd:.0}"), "55001");
+    assert_eq!(format!("{mjd:>10.2}"), "  55001.00");
+    assert_eq!("55000.".parse::<Mjd>().unwrap(), Mjd::new(55000, 0.0));
+
+    for bad in ["", ".5", "-1.5", "55000.5.5", "55000.5e3", "55000. 5"] {
+        assert!(bad.parse::<Mjd>().is_err(), "'{bad}' should fail");
+    }
+}
+
+#[test]
+fn mjd_arithmetic() {
+    let mjd = "55000.5".parse::<Mjd>().unwrap();
+
+    let delta = TimeDelta::from_seconds(43_200.0) + TimeDelta::from_nanos(1);
+    let later = mjd + delta;
+    assert_eq!((later.int(), later.picoseconds()), (55001, 1000));
+    assert_eq!(
+        later - mjd,
+        TimeDelta::from_picoseconds(43_200_000_000_001_000)
+    );
+    assert_eq!(mjd - later, -(later - mjd));
+    assert_eq!(later - delta, mjd);
+
+    let mut moved = mjd;
+    for _ in 0..1_000_000 {
+        moved += TimeDelta::from_nanos(1);
+    }
+    assert_eq!(moved - mjd, TimeDelta::from_seconds(0.001));
+    moved -= TimeDelta::from_days(1.0);
+    assert_eq!(moved.to_string(), "54999.50000001157407407");
+
+    assert!(mjd.checked_sub(TimeDelta::from_days(55_001.0)).is_none());
+    assert!(mjd.checked_add(TimeDelta::from_seconds(1e25)).is_none());
+
+    assert_eq!(
+        TimeDelta::checked_from_seconds(-1.5),
+        Some(TimeDelta::from_nanos(-1_500_000_000))
+    );
+    assert_eq!(
+        TimeDelta::checked_from_seconds(1e26).map(|d| d.picoseconds()),
+        Some(100_000_000_000_000_004_764_729_344 * 1_000_000_000_000)
+    );
+    for seconds in [1e27, -1e30, f64::INFINITY, f64::NEG_INFINITY, f64::NAN] {
+        assert_eq!(TimeDelta::checked_from_seconds(seconds), None);
+        assert_eq!(TimeDelta::checked_from_days(seconds), None);
+    }
+    assert_eq!(
+        Mjd::from_picoseconds(1, 86_400_000_000_000_000 * 2 + 5),
+        Mjd::from_picoseconds(3, 5)
+    );
+
+    let mut mjds = ["55000.5", "54000.25", "55000.25", "55000.5000000000001"]
+        .map(|t| t.parse::<Mjd>().unwrap());
+    mjds.sort();
+    assert_eq!(
+        mjds.map(|m| m.to_string()),
+        ["54000.25", "55000.25", "55000.5", "55000.5000000000001"]
+    );
+    let unique = mjds
+        .iter()
+        .chain(&mjds)
+        .collect::<std::collections::HashSet<_>>();
+    assert_eq!(unique.len(), 4);
+
+    assert_eq!(TimeDelta::from_seconds(-1.25).to_string(), "-1.25 s");
+    assert_eq!(TimeDelta::from_days(0.5), TimeDelta::from_seconds(43_200.0));
+    assert_eq!(TimeDelta::ZERO.to_string(), "0 s");
+}
+
+#[test]
+fn calendar() {
+    let mjd = |text: &str| text.parse::<Mjd>().unwrap();
+    let date = |text: &str| text.parse::<DateTime>().unwrap();
+
+    for (text, expected) in [
+        ("1858-11-17", "0.0"),
+        ("2000-01-01T12:00:00Z", "51544.5"),
+        ("2000-060", "51603.0"),
+        ("2016-12-31T23:59:60", "57753.99998842605988356"),
+    ] {
+        let converted = Mjd::try_from(date(text)).unwrap();
+        assert_eq!(converted, mjd(expected), "{text}");
+    }
+
+    let tiny = date("2017-01-01 00:00:00.000000000001");
+    assert_eq!(Mjd::try_from(tiny).ok(), Mjd::from_picoseconds(57754, 1));
+
+    let leap = date("2016-12-31T23:59:60.999999999999");
+    assert_eq!(DateTime::from(Mjd::try_from(leap).unwrap()), leap);
+    assert_eq!(
+        DateTime::from(mjd("57753.5")).to_string(),
+        "2016-12-31T12:00:00.5"
+    );
+    for text in ["55000.123456789012", "57753.9999999999999", "57754.5"] {
+        let dt = DateTime::from(mjd(text));
+        assert_eq!(Mjd::try_from(dt).unwrap(), mjd(text), "{dt}");
+    }
+
+    for bad in ["2015-12-31T23:59:60", "1858-11-16T23:59:59"] {
+        assert!(Mjd::try_from(date(bad)).is_err(), "'{bad}' should fail");
+    }
+    for bad in [
+        "2023-02-29",
+        "2024-367",
+        "2024-2-01",
+        "2024-01-01T24:00",
+        "2024-01-01T12:00.5",
+        "2024-01-01T12:30:60",
+        "2024-01-01T12:00:00.1234567890123",
+        "24-01-01",
+    ] {
+        assert!(bad.parse::<DateTime>().is_err(), "'{bad}' should fail");
+    }
+
+    let dt = date("2024-366T06:30:15.25");
+    assert_eq!((dt.month, dt.day, dt.day_of_year()), (12, 31, 366));
+    assert_eq!(format!("{dt:.1}"), "2024-12-31T06:30:15.2");
+    assert_eq!(format!("{dt:#}"), "2024-366T06:30:15.25");
+    assert_eq!(DateTime::from_day_of_year(2023, 366), None);
+
+    let jd = Jd::from(mjd("51544.5"));
+    assert_eq!(jd.to_string(), "2451545.0");
+    assert_eq!("2400000.5".parse::<Jd>().unwrap().0, mjd("0.0"));
+    assert!("2400000.4".parse::<Jd>().is_err());
+    for text in ["2451545.0", "2457754.2500000000000001", "2457754.9999"] {
+        assert_eq!(text.parse::<Jd>().unwrap().to_string(), text);
+    }
+
+    let year = mjd("51727.0").decimal_year();
+    assert!((year - 2000.5).abs() < 1e-12, "{year}");
+    assert_eq!(Mjd::from_decimal_year(2000.5), Some(mjd("51727.0")));
+    assert_eq!(Mjd::from_decimal_year(1800.0), None);
+    assert_eq!(Mjd::from_decimal_year(f64::NAN), None);
+    assert_eq!(Mjd::from_decimal_year(f64::INFINITY), None);
+    assert_eq!(Mjd::from_decimal_year(1e12), None);
+    assert_eq!(Mjd::from_decimal_year(-1e12), None);
+    assert_eq!(Mjd::from_decimal_year(2e9), None);
+    assert!(Mjd::from_decimal_year(1e6).is_some());
+}
+
+#[test]
+fn calendar_leap_days() {
+    // Every picosecond of the MJD makes it there and back
+    for ps in (0..86_400_000_000_000_000).step_by(999_999_999_989) {
+        let mjd = Mjd::from_picoseconds(57753, ps).unwrap();
+        assert_eq!(Mjd::try_from(DateTime::from(mjd)).unwrap(), mjd);
+    }
+    let last = Mjd::from_picoseconds(57753, 86_399_999_999_999_999).unwrap();
+    assert_eq!(Mjd::try_from(DateTime::from(last)).unwrap(), last);
+
+    // There are more picoseconds in the day than in the MJD
+    let noon = "2016-12-31T12:00:00".parse::<DateTime>().unwrap();
+    let mut mjds = (0..86_401)
+        .map(|ps| {
+            Mjd::try_from(DateTime {
+                picosecond: ps,
+                ..noon
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    mjds.dedup();
+    assert_eq!(mjds.len(), 86_400);
+
+    // While on other days, the two are the same
+    let noon = DateTime { day: 30, ..noon };
+    for ps in 0..1000 {
+        let date = DateTime {
+            picosecond: ps,
+            ..noon
+        };
+        assert_eq!(DateTime::from(Mjd::try_from(date).unwrap()), date);
+    }
+}
+
+#[test]
+fn time_scales() {
+    use crate::error::PsruError;
+
+    let mjd = |text: &str| text.parse::<Mjd>().unwrap();
+    let seconds = |delta: TimeDelta| delta.as_seconds();
+
+    // SOFA's test of `dtdb`, less its few µs from the site
+    let tt = Epoch::<Tt>::new(mjd("48939.123"));
+    let tdb = tt.to::<Tdb>().unwrap();
+    assert!((seconds(tdb.mjd() - tt.mjd()) + 1.280_37e-3).abs() < 3e-6);
+
+    let j2000 = Epoch::<Tt>::new(mjd("51544.5"));
+    let tdb = j2000.to::<Tdb>().unwrap();
+    let tcb = j2000.to::<Tcb>().unwrap();
+    assert!((seconds(tcb.mjd() - tdb.mjd()) - 11.253_79).abs() < 1e-5);
+    let tcg = j2000.to::<Tcg>().unwrap();
+    assert!((seconds(tcg.mjd() - j2000.mjd()) - 0.505_83).abs() < 1e-5);
+
+    let utc = Epoch::<Utc>::new(mjd("51544.5"));
+    let tai = utc.to::<Tai>().unwrap();
+    assert_eq!(tai.mjd() - utc.mjd(), TimeDelta::from_seconds(32.0));
+
+    // Across the leap second at the end of 2016, which makes 0.1 of that
+    // day 0.1 s longer
+    let before = Epoch::<Utc>::new(mjd("57753.9"));
+    let after = Epoch::<Utc>::new(mjd("57754.1"));
+    let tai = (before.to::<Tai>().unwrap(), after.to::<Tai>().unwrap());
+    assert_eq!(tai.1 - tai.0, TimeDelta::from_nanos(17_280_100_000_000));
+    assert_eq!(after - before, TimeDelta::from_days(0.2));
+
+    for text in ["41317.0", "57753.99999", "57753.999999999", "60000.123456"] {
+        let utc = Epoch::<Utc>::new(mjd(text));
+        let back = |epoch: Result<Epoch<Utc>, _>| {
+            let delta = epoch.unwrap() - utc;
+            assert!(delta.picoseconds().abs() <= 2, "{utc}: {delta}");
+        };
+        back(utc.to::<Tai>().and_then(Epoch::to));
+        back(utc.to::<Tt>().and_then(Epoch::to));
+        back(utc.to::<Tcg>().and_then(Epoch::to));
+        back(utc.to::<Tdb>().and_then(Epoch::to));
+        back(utc.to::<Tcb>().and_then(Epoch::to));
+    }
+
+    let old = Epoch::<Utc>::new(mjd("41316.9"));
+    assert!(matches!(old.to::<Tai>(), Err(PsruError::TimeOutOfRange(_))));
+    assert_eq!(old.to_string(), "41316.9 UTC");
+    assert!(Epoch::<Tt>::new(mjd("0.0")).to::<Tai>().is_err());
+}
+
+#[test]
+fn leap_seconds() {
+    use crate::error::PsruError;
+
+    let mjd = |text: &str| text.parse::<Mjd>().unwrap();
+    let builtin = LeapSeconds::default();
+    assert_eq!(builtin.changes().len(), 28);
+    assert_eq!(builtin.expires(), mjd("61584.0"));
+    assert_eq!(builtin.day_seconds(mjd("57203.2")).unwrap(), 86_401);
+    assert_eq!(builtin.day_seconds(mjd("57204.2")).unwrap(), 86_400);
+    assert!(builtin.covers(mjd("61584.9")));
+    assert!(matches!(
+        builtin.tai_utc(mjd("61585.0")),
+        Err(PsruError::LeapSecondsExpired(..))
+    ));
+    assert!(matches!(
+        builtin.tai_utc(mjd("41316.0")),
+        Err(PsruError::TimeOutOfRange(_))
+    ));
+
+    // Tempo2 style, without an expiry
+    let tempo2 = "# MJD TAI-UTC\n56109 35\n57204 36\n\n57754 37\n";
+    assert!(matches!(
+        LeapSeconds::read(tempo2.as_bytes()),
+        Err(PsruError::LeapSecondsNoExpiry)
+    ));
+    let leaps =
+        LeapSeconds::read_with_expiry(tempo2.as_bytes(), mjd("61000.0"))
+            .unwrap();
+    assert_eq!(leaps.expires(), mjd("61000.0"));
+    assert!(matches!(
+        leaps.tai_utc(mjd("70000.0")),
+        Err(PsruError::LeapSecondsExpired(..))
+    ));
+    assert_eq!(
+        leaps.tai_utc(mjd("60000.0")).unwrap(),
+        TimeDelta::from_seconds(37.0)
+    );
+    assert_eq!(leaps.day_seconds(mjd("57753.0")).unwrap(), 86_401);
+
+    // The given expiry wins over the stated one
+    let stated = format!("# File expires on 28 June 2027\n{tempo2}");
+    let leaps =
+        LeapSeconds::read_with_expiry(stated.as_bytes(), mjd("61000.0"))
+            .unwrap();
+    assert_eq!(leaps.expires(), mjd("61000.0"));
+
+    let leaps = leaps.with_expiry(mjd("62000.0"));
+    let late = Epoch::<Utc>::new(mjd("61800.5"));
+    assert!(matches!(
+        late.to::<Tai>(),
+        Err(PsruError::LeapSecondsExpired(..))
+    ));
+    let tai = late.to_using::<Tai>(&leaps).unwrap();
+    assert_eq!(
+        tai - Epoch::new(mjd("61800.5")),
+        TimeDelta::from_seconds(37.0)
+    );
+    assert_eq!(tai.to_using::<Utc>(&leaps).unwrap(), late);
+
+    for bad in [
+        "41499 11\n41317 10\n",
+        "41317.5 10\n",
+        "41317 10 1\n",
+        "41317 ten\n",
+        "# File expires on 31 Juno 2026\n",
+    ] {
+        let result = LeapSeconds::read(bad.as_bytes());
+        assert!(matches!(result, Err(PsruError::Unparsable { .. })), "{bad}");
+    }
+    let missing = LeapSeconds::read_path(std::path::Path::new("no/leap.sec"));
+    assert!(matches!(missing, Err(PsruError::IOError(_))));
+}
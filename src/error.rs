@@ -3,40 +3,57 @@
 
 use std::error::Error;
 
+use crate::data_types::Mjd;
+use crate::warning::{Warning, WarningKind};
+
 #[derive(Debug)]
 pub enum PsruError {
     Unparsable {
         value: String,
         to_type: &'static str,
+        /// Only set when reading `.par` files.
+        ctx: Option<ParContext>,
     },
     IOError(std::io::Error),
+    BadSelector(String),
+    InvalidDateTime(String),
+    TimeOutOfRange(String),
+    LeapSecondsExpired(Mjd, Mjd),
+    LeapSecondsNoExpiry,
+    ClockOutOfRange {
+        mjd: Mjd,
+        /// Like `UTC(GBT) -> UTC(GPS)`.
+        clocks: String,
+        span: Option<(Mjd, Mjd)>,
+    },
+    ClockChainBroken(String),
 
     // Par errors ---------------------------------
-    InvalidRA(String),
-    InvalidDec(String),
-    ParMissingValue(String),
+    InvalidRA(Option<ParContext>, String),
+    InvalidDec(Option<ParContext>, String),
+    ParMissingValue(Option<ParContext>, String),
 
-    ParUnknownFlag(String),
-    ParUnrecognisedKey(String),
-    UnknownBinaryModel(String),
-    UnknownTimeEphemeris(String),
-    UnknownT2CMethod(String),
-    UnknownUnits(String),
-    UnknownErrorMode(String),
+    ParUnknownFlag(Option<ParContext>, String),
+    ParUnrecognisedKey(Option<ParContext>, String),
+    UnknownBinaryModel(Option<ParContext>, String),
+    UnknownTimeEphemeris(Option<ParContext>, String),
+    UnknownT2CMethod(Option<ParContext>, String),
+    UnknownUnits(Option<ParContext>, String),
+    UnknownErrorMode(Option<ParContext>, String),
 
-    IncompleteJump(String),
-    BadGlitch(usize),
+    IncompleteJump(Option<ParContext>, String),
+    BadGlitch(Option<ParContext>, usize),
 
-    ParNoName,
-    ParNoFrequency,
-    ParNoPEpoch,
-    ParNoDispersion,
+    ParNoName(Option<ParContext>),
+    ParNoFrequency(Option<ParContext>),
+    ParNoPEpoch(Option<ParContext>),
+    ParNoDispersion(Option<ParContext>),
 
-    ParBadFrequency,
-    ParBadPEpoch,
+    ParBadFrequency(Option<ParContext>),
+    ParBadPEpoch(Option<ParContext>),
 
-    ParDuplicateParameters(Vec<(String, String)>),
-    ParRepeatParam(String),
+    ParDuplicateParameters(Option<ParContext>, Vec<(String, String)>),
+    ParRepeatParam(Option<ParContext>, String),
 
     // Tim errors ---------------------------------
     OrphanFile,
@@ -47,49 +64,102 @@ pub enum PsruError {
     TimNotAscii(Option<TimContext>),
     TimParkesMissingBlank(Option<TimContext>),
     TimParkesMissingPeriod(Option<TimContext>),
+    TimBadColumn(Option<TimContext>, &'static str, String),
+    TimBadDirective(Option<TimContext>, String),
+    TimDirectiveOutOfRange(Option<TimContext>, String),
+    TimIncludeCycle(Option<TimContext>, Vec<String>),
+    TimIncludeTooDeep(Option<TimContext>, Vec<String>),
 }
 impl std::fmt::Display for PsruError {
+    #[allow(clippy::too_many_lines)]
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(ctx) = self.par_ctx() {
+            write!(f, "{ctx} ")?;
+        }
+
         match self {
-            Self::Unparsable { value, to_type } => {
+            Self::Unparsable { value, to_type, .. } => {
                 write!(f, "Impossible to parse '{value}' into type {to_type}.")
             }
             Self::IOError(error) => write!(f, "IO error >> {error}"),
+            Self::BadSelector(selector) => {
+                write!(f, "Could not understand selector '{selector}'.")
+            }
+            Self::InvalidDateTime(date) => {
+                write!(f, "Invalid or out of range date-time '{date}'.")
+            }
+            Self::TimeOutOfRange(time) => {
+                write!(f, "Time '{time}' is outside of the supported range.")
+            }
+            Self::LeapSecondsExpired(mjd, expires) => write!(
+                f,
+                "No leap seconds known for MJD {mjd}, as the table expired \
+                 after MJD {expires}."
+            ),
+            Self::LeapSecondsNoExpiry => write!(
+                f,
+                "The leap second table does not state when it expires."
+            ),
+            Self::ClockOutOfRange { mjd, clocks, span } => {
+                write!(
+                    f,
+                    "MJD {mjd} is not covered by the clock file {clocks}"
+                )?;
+                match span {
+                    Some((first, last)) => {
+                        write!(f, ", which spans MJD {first} to {last}.")
+                    }
+                    None => write!(f, ", which is empty."),
+                }
+            }
+            Self::ClockChainBroken(reason) => {
+                write!(f, "Could not chain clock files: {reason}.")
+            }
 
-            Self::InvalidRA(ra) => write!(f, "Invalid RA string '{ra}'."),
-            Self::InvalidDec(dec) => write!(f, "Invalid DEC string '{dec}'."),
-            Self::ParMissingValue(p) => write!(f, "Param '{p}' missing value."),
+            Self::InvalidRA(_, ra) => write!(f, "Invalid RA string '{ra}'."),
+            Self::InvalidDec(_, dec) => {
+                write!(f, "Invalid DEC string '{dec}'.")
+            }
+            Self::ParMissingValue(_, p) => {
+                write!(f, "Param '{p}' missing value.")
+            }
 
-            Self::ParUnknownFlag(flag) => write!(f, "Unknown flag '{flag}'."),
-            Self::ParUnrecognisedKey(k) => write!(f, "Unrecognised key '{k}'."),
-            Self::UnknownBinaryModel(m) => {
+            Self::ParUnknownFlag(_, flag) => {
+                write!(f, "Unknown flag '{flag}'.")
+            }
+            Self::ParUnrecognisedKey(_, k) => {
+                write!(f, "Unrecognised key '{k}'.")
+            }
+            Self::UnknownBinaryModel(_, m) => {
                 write!(f, "Unknown binary model '{m}'.")
             }
-            Self::UnknownTimeEphemeris(te) => {
+            Self::UnknownTimeEphemeris(_, te) => {
                 write!(f, "Unknown time ephemeris '{te}'.")
             }
-            Self::UnknownT2CMethod(t2cm) => {
+            Self::UnknownT2CMethod(_, t2cm) => {
                 write!(f, "Unknown t2CMethod '{t2cm}'.")
             }
-            Self::UnknownErrorMode(em) => {
+            Self::UnknownErrorMode(_, em) => {
                 write!(f, "Unknown error mode '{em}'.")
             }
-            Self::UnknownUnits(u) => write!(f, "Unknown units '{u}'."),
+            Self::UnknownUnits(_, u) => write!(f, "Unknown units '{u}'."),
 
-            Self::IncompleteJump(j) => write!(f, "Incomplete jump '{j}'."),
-            Self::BadGlitch(g) => {
+            Self::IncompleteJump(_, j) => {
+                write!(f, "Incomplete or malformed jump '{j}'.")
+            }
+            Self::BadGlitch(_, g) => {
                 write!(f, "Glitch with index {g} is incomplete.")
             }
 
-            Self::ParNoName => write!(f, "Missing PSR parameter."),
-            Self::ParNoFrequency => write!(f, "Missing F0 parameter."),
-            Self::ParNoPEpoch => write!(f, "Missing PEPOCH parameter."),
-            Self::ParNoDispersion => write!(f, "Missing DM parameter."),
+            Self::ParNoName(_) => write!(f, "Missing PSR parameter."),
+            Self::ParNoFrequency(_) => write!(f, "Missing F0 parameter."),
+            Self::ParNoPEpoch(_) => write!(f, "Missing PEPOCH parameter."),
+            Self::ParNoDispersion(_) => write!(f, "Missing DM parameter."),
 
-            Self::ParBadPEpoch => write!(f, "Bad PEPOCH parameter."),
-            Self::ParBadFrequency => write!(f, "Bad F0 parameter."),
+            Self::ParBadPEpoch(_) => write!(f, "Bad PEPOCH parameter."),
+            Self::ParBadFrequency(_) => write!(f, "Bad F0 parameter."),
 
-            Self::ParDuplicateParameters(items) => write!(
+            Self::ParDuplicateParameters(_, items) => write!(
                 f,
                 "There are duplicate parameters defined:{}",
                 items.iter().fold(String::new(), |a, (l1, l2)| format!(
@@ -97,7 +167,7 @@ impl std::fmt::Display for PsruError {
                 ))
             ),
 
-            Self::ParRepeatParam(param) => {
+            Self::ParRepeatParam(_, param) => {
                 write!(f, "Repeated '{param}' parameter.")
             }
 
@@ -143,16 +213,170 @@ impl std::fmt::Display for PsruError {
                 "{} There's supposed to be a period in the column 42.",
                 tim_ctx(ctx.as_ref())
             ),
+            Self::TimBadColumn(ctx, field, value) => write!(
+                f,
+                "{} Bad {} column '{}'.",
+                tim_ctx(ctx.as_ref()),
+                field,
+                value
+            ),
+            Self::TimBadDirective(ctx, directive) => write!(
+                f,
+                "{} Could not understand directive '{}'.",
+                tim_ctx(ctx.as_ref()),
+                directive
+            ),
+            Self::TimDirectiveOutOfRange(ctx, directive) => write!(
+                f,
+                "{} Directive '{}' moves the TOA out of range.",
+                tim_ctx(ctx.as_ref()),
+                directive
+            ),
+            Self::TimIncludeCycle(ctx, chain) => write!(
+                f,
+                "{} File includes itself: {}.",
+                tim_ctx(ctx.as_ref()),
+                chain.join(" -> ")
+            ),
+            Self::TimIncludeTooDeep(ctx, chain) => write!(
+                f,
+                "{} Includes are nested too deeply: {}.",
+                tim_ctx(ctx.as_ref()),
+                chain.join(" -> ")
+            ),
         }
     }
 }
 impl PsruError {
+    pub(crate) fn unparsable(value: &str, to_type: &'static str) -> Self {
+        Self::Unparsable {
+            value: value.to_string(),
+            to_type,
+            ctx: None,
+        }
+    }
+
+    /// The position in a `.par` file that caused this error, if any.
+    pub const fn par_ctx(&self) -> Option<&ParContext> {
+        match self {
+            Self::Unparsable { ctx, .. }
+            | Self::InvalidRA(ctx, _)
+            | Self::InvalidDec(ctx, _)
+            | Self::ParMissingValue(ctx, _)
+            | Self::ParUnknownFlag(ctx, _)
+            | Self::ParUnrecognisedKey(ctx, _)
+            | Self::UnknownBinaryModel(ctx, _)
+            | Self::UnknownTimeEphemeris(ctx, _)
+            | Self::UnknownT2CMethod(ctx, _)
+            | Self::UnknownUnits(ctx, _)
+            | Self::UnknownErrorMode(ctx, _)
+            | Self::IncompleteJump(ctx, _)
+            | Self::BadGlitch(ctx, _)
+            | Self::ParNoName(ctx)
+            | Self::ParNoFrequency(ctx)
+            | Self::ParNoPEpoch(ctx)
+            | Self::ParNoDispersion(ctx)
+            | Self::ParBadFrequency(ctx)
+            | Self::ParBadPEpoch(ctx)
+            | Self::ParDuplicateParameters(ctx, _)
+            | Self::ParRepeatParam(ctx, _) => ctx.as_ref(),
+
+            _ => None,
+        }
+    }
+
+    /// Renders the error like a compiler diagnostic, i.e. with the offending
+    /// line of a `.par` file and the offending part of it underlined. Errors
+    /// without a par context are rendered like their `Display`.
+    ///
+    /// ```text
+    /// error: Impossible to parse '9OO1' into type double.
+    ///  --> J0000-9999.par:5:8
+    ///   |
+    /// 5 | F0     9OO1 1 0.0001
+    ///   |        ^^^^
+    /// ```
+    pub fn diagnostic(&self) -> String {
+        let Some(ctx) = self.par_ctx() else {
+            return format!("error: {self}");
+        };
+
+        // Avoid repeating the location, which is rendered separately
+        let message = self.to_string();
+        let message =
+            message.strip_prefix(&format!("{ctx} ")).unwrap_or(&message);
+
+        ctx.render(message)
+    }
+
+    /// Sets the par context if not already set. The offending token is
+    /// guessed from the contents of the error.
+    pub(crate) fn set_par_ctx(mut self, ctx: &ParContext) -> Self {
+        let offending = match &self {
+            Self::Unparsable { value, .. }
+            | Self::InvalidRA(_, value)
+            | Self::InvalidDec(_, value)
+            | Self::ParMissingValue(_, value)
+            | Self::ParUnknownFlag(_, value)
+            | Self::ParUnrecognisedKey(_, value)
+            | Self::UnknownBinaryModel(_, value)
+            | Self::UnknownTimeEphemeris(_, value)
+            | Self::UnknownT2CMethod(_, value)
+            | Self::UnknownUnits(_, value)
+            | Self::UnknownErrorMode(_, value)
+            | Self::ParRepeatParam(_, value) => Some(value.clone()),
+            Self::ParDuplicateParameters(_, items) => items
+                .first()
+                .and_then(|(_, p)| p.split_whitespace().next())
+                .map(str::to_string),
+            _ => None,
+        };
+
+        let (Self::Unparsable { ctx: old_ctx, .. }
+        | Self::InvalidRA(old_ctx, _)
+        | Self::InvalidDec(old_ctx, _)
+        | Self::ParMissingValue(old_ctx, _)
+        | Self::ParUnknownFlag(old_ctx, _)
+        | Self::ParUnrecognisedKey(old_ctx, _)
+        | Self::UnknownBinaryModel(old_ctx, _)
+        | Self::UnknownTimeEphemeris(old_ctx, _)
+        | Self::UnknownT2CMethod(old_ctx, _)
+        | Self::UnknownUnits(old_ctx, _)
+        | Self::UnknownErrorMode(old_ctx, _)
+        | Self::IncompleteJump(old_ctx, _)
+        | Self::BadGlitch(old_ctx, _)
+        | Self::ParNoName(old_ctx)
+        | Self::ParNoFrequency(old_ctx)
+        | Self::ParNoPEpoch(old_ctx)
+        | Self::ParNoDispersion(old_ctx)
+        | Self::ParBadFrequency(old_ctx)
+        | Self::ParBadPEpoch(old_ctx)
+        | Self::ParDuplicateParameters(old_ctx, _)
+        | Self::ParRepeatParam(old_ctx, _)) = &mut self
+        else {
+            return self;
+        };
+
+        if old_ctx.is_none() {
+            *old_ctx = Some(ctx.pointing_at(offending.as_deref()));
+        }
+
+        self
+    }
+
     pub(crate) fn set_tim_ctx(mut self, ctx: &TimContext) -> Self {
         let (Self::TimUnexpectedEOL(old_ctx)
         | Self::TimNotAscii(old_ctx)
         | Self::TimMalformedMJD(old_ctx)
         | Self::TimUnvaluedFlag(old_ctx, _)
-        | Self::TimFormatDiscrepancy(old_ctx, _)) = &mut self
+        | Self::TimFormatDiscrepancy(old_ctx, _)
+        | Self::TimParkesMissingBlank(old_ctx)
+        | Self::TimParkesMissingPeriod(old_ctx)
+        | Self::TimBadColumn(old_ctx, _, _)
+        | Self::TimBadDirective(old_ctx, _)
+        | Self::TimDirectiveOutOfRange(old_ctx, _)
+        | Self::TimIncludeCycle(old_ctx, _)
+        | Self::TimIncludeTooDeep(old_ctx, _)) = &mut self
         else {
             return self;
         };
@@ -186,6 +410,9 @@ impl TimContext {
     pub(crate) fn line(&mut self, number: usize) {
         self.line = number;
     }
+    pub(crate) fn warning(&self, kind: WarningKind) -> Warning {
+        Warning::new(&self.fname, self.line, kind)
+    }
 }
 impl std::fmt::Display for TimContext {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -196,3 +423,125 @@ impl std::fmt::Display for TimContext {
 fn tim_ctx(ctx: Option<&TimContext>) -> String {
     ctx.map_or_else(String::new, TimContext::to_string)
 }
+
+/// Where in a `.par` file an error occurred.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParContext {
+    source: String,
+    line: usize,
+    text: String,
+    columns: (usize, usize),
+}
+impl ParContext {
+    /// A context for a specific line. Points at the whole line, until told
+    /// otherwise.
+    pub(crate) fn new(source: &str, line: usize, text: &str) -> Self {
+        let start = text.len() - text.trim_start().len();
+        let end = text.trim_end().len().max(start);
+
+        Self {
+            source: source.to_string(),
+            line,
+            text: text.to_string(),
+            columns: (start, end),
+        }
+    }
+
+    /// A context for the whole file, e.g. for missing parameters.
+    pub(crate) fn file(source: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            line: 0,
+            text: String::new(),
+            columns: (0, 0),
+        }
+    }
+
+    /// Points at the first whole token matching `token`, if there is one.
+    fn pointing_at(&self, token: Option<&str>) -> Self {
+        let mut ctx = self.clone();
+        let Some(token) = token.filter(|t| !t.is_empty()) else {
+            return ctx;
+        };
+
+        let mut offset = 0;
+        for part in self.text.split_whitespace() {
+            let start = offset + self.text[offset..].find(part).unwrap_or(0);
+            offset = start + part.len();
+            if part == token {
+                ctx.columns = (start, offset);
+                break;
+            }
+        }
+
+        ctx
+    }
+
+    /// The name of the source, e.g. a file path. May be empty.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The line number, starting at 1. It is 0 if the error concerns the
+    /// whole file.
+    pub const fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The text of the offending line.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The (byte) columns of the offending part of the line, as a half-open
+    /// range starting at 0.
+    pub const fn columns(&self) -> (usize, usize) {
+        self.columns
+    }
+
+    /// Renders a message with the offending line, and the offending part
+    /// underlined.
+    pub fn render(&self, message: &str) -> String {
+        let source = if self.source.is_empty() {
+            "<par>"
+        } else {
+            &self.source
+        };
+
+        if self.line == 0 {
+            return format!("error: {message}\n --> {source}\n");
+        }
+
+        let number = self.line.to_string();
+        let gutter = " ".repeat(number.len());
+        let (start, end) = self.columns;
+        let lead = self.text[..start]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        let carets = "^".repeat(self.text[start..end].chars().count().max(1));
+
+        format!(
+            "error: {message}\n\
+            {gutter}--> {source}:{}:{}\n\
+            {gutter} |\n\
+            {number} | {}\n\
+            {gutter} | {lead}{carets}\n",
+            self.line,
+            start + 1,
+            self.text,
+        )
+    }
+}
+impl std::fmt::Display for ParContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.source.is_empty(), self.line) {
+            (true, 0) => write!(f, "In .par file:"),
+            (true, line) => write!(f, "On line {line}:"),
+            (false, 0) => write!(f, "In file '{}':", self.source),
+            (false, line) => {
+                write!(f, "In file '{}' on line {line}:", self.source)
+            }
+        }
+    }
+}
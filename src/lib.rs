@@ -4,8 +4,12 @@
 #![allow(clippy::must_use_candidate)]
 
 pub(crate) mod parse_tools;
+#[cfg(test)]
+mod test_dir;
 
+pub mod clock;
 pub mod data_types;
 pub mod error;
 pub mod parfile;
 pub mod timfile;
+pub mod warning;
@@ -46,20 +46,29 @@
 use std::io::{BufRead, BufWriter, Write};
 
 pub use glitch::Glitch;
-pub use jump::Jump;
+pub use jump::{Jump, JumpSelection, JumpType};
+pub use layout::Layout;
+use layout::{EntryKey, SourceLine, is_verbatim, strip_comment};
 use parameters::{
-    COORDS, FittedParameter, J2000Fit, parse_coord, parse_count, parse_fitted,
-    parse_flag, parse_text,
+    COORDS, FittedParameter, J2000Fit, is_deprecated_alias, is_flag,
+    parse_coord, parse_count, parse_fitted, parse_flag, parse_text,
+    suspicious_value,
+};
+pub use parameters::{
+    FittedParameterValue, Parameter, ParameterInfo, ParameterKind,
 };
-pub use parameters::{FittedParameterValue, Parameter};
 
 use crate::{
     data_types::{DECCoordType, RACoordType},
-    error::PsruError,
+    error::{ParContext, PsruError},
+    parse_tools::format_f64,
+    warning::{Warning, WarningKind},
 };
 
+mod access;
 mod glitch;
 mod jump;
+mod layout;
 mod parameters;
 mod tests;
 
@@ -117,6 +126,65 @@ pub enum Units {
     TDB,
 }
 
+/// Options for reading `.par` files.
+#[derive(Debug, Default, Clone)]
+pub struct ParseOptions {
+    /// Whether to keep unrecognised keys (with a warning) instead of
+    /// returning an error.
+    pub lenient: bool,
+    /// A name for what is being read, e.g. the file path. It's used for
+    /// warnings and errors.
+    pub source: String,
+}
+impl ParseOptions {
+    /// Unrecognised keys are errors. This is the default.
+    pub const fn strict() -> Self {
+        Self {
+            lenient: false,
+            source: String::new(),
+        }
+    }
+
+    /// Unrecognised keys are kept as [`ExtraParameter`]s.
+    pub const fn lenient() -> Self {
+        Self {
+            lenient: true,
+            source: String::new(),
+        }
+    }
+
+    /// Sets the name of what is being read.
+    #[must_use]
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.source = source.to_string();
+        self
+    }
+}
+
+/// A line with an unrecognised key, kept as-is when reading in lenient mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtraParameter {
+    /// The line number (starting at 1) it was read from.
+    pub line: usize,
+    /// The key, as it was written.
+    pub key: String,
+    /// Everything after the key, as it was written.
+    pub value: String,
+}
+impl ExtraParameter {
+    fn new(text: &str, line: usize) -> Self {
+        let text = text.trim();
+        let (key, value) =
+            text.split_once(char::is_whitespace).unwrap_or((text, ""));
+
+        Self {
+            line,
+            key: key.to_string(),
+            value: value.trim_start().to_string(),
+        }
+    }
+}
+
 /// Complete representation of a loaded .par file.
 ///
 /// It follows the loose standards of TEMPO2, and as such is guaranteed to have
@@ -128,9 +196,12 @@ pub enum Units {
 /// parameters, they are kept track of with indices (e.g. `GLEP_1`) and
 /// disjunct ranges are considered erroneous.
 ///
-/// All fields are public, since it is essentially just a datafile. There is,
+/// All values are public, since it is essentially just a datafile. There is,
 /// however, a check of all values performed before writing. A failure in this
 /// results in an error and no write.
+///
+/// The layout of the file it was read from is also remembered, so that it
+/// can be written back with minimal changes, see [`Parfile::write_lossless`].
 #[derive(Debug, Default)]
 pub struct Parfile {
     /// J2000 right ascension (hh:mm:ss.sss)
@@ -153,6 +224,9 @@ pub struct Parfile {
     /// Jumps, if any
     pub jumps: Vec<Jump>,
 
+    /// Lines with unrecognised keys, kept when reading in lenient mode.
+    pub extras: Vec<ExtraParameter>,
+
     /// Which time ephemeris to use
     pub time_eph: TimeEphemeris,
     /// Binary model
@@ -164,6 +238,9 @@ pub struct Parfile {
     pub units: Units,
     /// Which error mode to use.
     pub error_mode: ErrorMode,
+
+    /// The lines as they were read, for lossless writing.
+    pub layout: Layout,
 }
 
 impl Parfile {
@@ -179,19 +256,125 @@ impl Parfile {
     /// `u32`, and a few have their own enums to avoid excessive `String`
     /// usage.
     pub fn read(reader: impl BufRead) -> Result<Self, PsruError> {
+        Self::read_with(reader, &ParseOptions::strict()).map(|(par, _)| par)
+    }
+
+    /// Reads a `BufReader` as a .par file, as specified by `options`.
+    ///
+    /// In lenient mode, lines with unrecognised keys are kept as they are in
+    /// [`Parfile::extras`], and a warning is produced for each of them. So
+    /// are glitches that are not defined enough, which are dropped.
+    ///
+    /// Recoverable oddities, in any mode, also produce warnings. These are
+    /// deprecated aliases, exact repeats of parameters (which are then
+    /// ignored), flags without a value (which are then set), and some
+    /// physically unlikely values.
+    ///
+    /// # Errors
+    /// Same as for [`Parfile::read`], except that unrecognised keys and
+    /// incomplete glitches are not errors in lenient mode.
+    pub fn read_with(
+        reader: impl BufRead,
+        options: &ParseOptions,
+    ) -> Result<(Self, Vec<Warning>), PsruError> {
         let mut par = Self::default();
+        let mut warnings = Vec::new();
 
-        for result in reader.lines() {
+        for (line_number, result) in reader.lines().enumerate() {
             let line = result?;
-            if line.is_empty() {
+            if is_verbatim(&line) {
+                par.layout.lines.push(SourceLine::verbatim(line));
                 continue;
             }
-            par.parse_line(&line)?;
+
+            let content = strip_comment(&line);
+            let mut warn = |kind| {
+                warnings.push(Warning::new(
+                    &options.source,
+                    line_number + 1,
+                    kind,
+                ));
+            };
+
+            // Flags are sometimes written without a value, meaning "set"
+            let first = content.split_whitespace().next().unwrap_or_default();
+            let parsed = if content.split_whitespace().count() == 1
+                && is_flag(first)
+            {
+                warn(WarningKind::EmptyFlag(first.to_string()));
+                par.parse_line(&format!("{first} Y"))
+            } else {
+                par.parse_line(content)
+            }
+            .map_err(|err| {
+                let ctx =
+                    ParContext::new(&options.source, line_number + 1, &line);
+                err.set_par_ctx(&ctx)
+            })?;
+
+            let key = match parsed {
+                Some(key) => key,
+                None if options.lenient => {
+                    let extra = ExtraParameter::new(content, line_number + 1);
+                    warn(WarningKind::UnknownParameter(extra.key.clone()));
+                    par.extras.push(extra);
+
+                    EntryKey::Extra(par.extras.len() - 1)
+                }
+                None => {
+                    let ctx = ParContext::new(
+                        &options.source,
+                        line_number + 1,
+                        &line,
+                    );
+                    return Err(PsruError::ParUnrecognisedKey(
+                        None,
+                        first.to_string(),
+                    )
+                    .set_par_ctx(&ctx));
+                }
+            };
+
+            if let EntryKey::Named(name) = key {
+                if is_deprecated_alias(first) {
+                    warn(WarningKind::DeprecatedAlias {
+                        alias: first.to_string(),
+                        name: name.to_string(),
+                    });
+                }
+                if par.drop_identical_repeat(name) {
+                    warn(WarningKind::DuplicateParameter(name.to_string()));
+                }
+                if let Some(reason) = par.suspicious(name) {
+                    warn(WarningKind::SuspiciousValue {
+                        name: name.to_string(),
+                        reason,
+                    });
+                }
+            }
+
+            let tokens = par.entry_tokens(&key).unwrap_or_default();
+            par.layout.lines.push(SourceLine::entry(line, key, tokens));
         }
 
-        par.check()?;
+        if options.lenient {
+            for number in par.drop_incomplete_glitches() {
+                // The glitch may have no lines if only later ones were given
+                let line = par.layout.lines.iter().position(|l| {
+                    matches!(l.key(), Some(EntryKey::Glitch(_, n)) if *n >= number)
+                });
+                warnings.push(Warning::new(
+                    &options.source,
+                    line.map_or(0, |i| i + 1),
+                    WarningKind::IncompleteGlitch(number),
+                ));
+            }
+        }
 
-        Ok(par)
+        par.check()
+            .map_err(|err| par.locate(err, &options.source))?;
+
+        Ok((par, warnings))
     }
 
     /// Writes itself to a stream.
@@ -213,7 +396,7 @@ impl Parfile {
             .texts
             .iter()
             .position(|t| t.name() == "PSR")
-            .ok_or(PsruError::ParNoName)?;
+            .ok_or(PsruError::ParNoName(None))?;
 
         let mut texts = self.texts.iter().collect::<Vec<_>>();
 
@@ -277,6 +460,13 @@ impl Parfile {
             ErrorMode::Mode1 => writer.write_all(b"MODE 1\n")?,
         }
 
+        // Anything unrecognised
+        for extra in &self.extras {
+            let line = format!("{} {}", extra.key, extra.value);
+            writer.write_all(line.trim_end().as_bytes())?;
+            writer.write_all(b"\n")?;
+        }
+
         // Glitches
         for glitch in &self.glitches {
             let lines = glitch.write();
@@ -294,50 +484,302 @@ impl Parfile {
         Ok(())
     }
 
-    fn parse_line(&mut self, line: &str) -> Result<(), PsruError> {
+    /// Writes itself to a stream, keeping the layout of the file it was read
+    /// from.
+    ///
+    /// Comments, blank lines, line order, the aliases used for keys (e.g.
+    /// `PSRJ` rather than `PSR`), and the exact text of values are all kept
+    /// for lines whose entries have not been changed since reading. Changed
+    /// entries are written into the same columns as before, removed ones are
+    /// left out, and new ones are appended at the end.
+    ///
+    /// For a `Parfile` that was not read from anything, this is the same as
+    /// [`Parfile::write`], save for the order of entries.
+    ///
+    /// # Errors
+    /// Same as for [`Parfile::write`].
+    pub fn write_lossless(
+        &self,
+        writer: &mut impl Write,
+    ) -> Result<(), PsruError> {
+        self.check()?;
+        let mut writer = BufWriter::new(writer);
+
+        for line in &self.layout.lines {
+            let current = line.key().and_then(|key| self.entry_tokens(key));
+            if let Some(text) = line.render(current.as_deref()) {
+                writer.write_all(text.as_bytes())?;
+                writer.write_all(b"\n")?;
+            }
+        }
+
+        for key in self.entries() {
+            if self.layout.lines.iter().any(|l| l.key() == Some(&key)) {
+                continue;
+            }
+            let Some(tokens) = self.entry_tokens(&key) else {
+                continue;
+            };
+            // Unset glitch fields are simply zero
+            if matches!(key, EntryKey::Glitch(..)) && tokens == ["0"] {
+                continue;
+            }
+
+            let key = match key {
+                EntryKey::Extra(index) => self.extras[index].key.clone(),
+                key => key.canonical(),
+            };
+            let line = format!("{key} {}\n", tokens.join(" "));
+            writer.write_all(line.as_bytes())?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// If the last parameter read with this name is an exact repeat of an
+    /// earlier one, it is removed. Returns whether that happened.
+    fn drop_identical_repeat(&mut self, name: &str) -> bool {
+        fn drop_last<T: PartialEq>(
+            params: &mut Vec<Parameter<T>>,
+            name: &str,
+        ) -> bool {
+            let Some((last, rest)) = params.split_last() else {
+                return false;
+            };
+            if last.name() != name
+                || !rest
+                    .iter()
+                    .any(|p| p.name() == name && p.value() == last.value())
+            {
+                return false;
+            }
+
+            params.pop();
+            true
+        }
+
+        drop_last(&mut self.parameters, name)
+            || drop_last(&mut self.counts, name)
+            || drop_last(&mut self.texts, name)
+            || drop_last(&mut self.flags, name)
+    }
+
+    /// Checks the value of a parameter for physically unlikely values, and
+    /// returns the reason if it is odd.
+    fn suspicious(&self, name: &str) -> Option<&'static str> {
+        let value =
+            match self.parameters.iter().find(|p| p.name() == name)?.value() {
+                FittedParameterValue::Missing => return None,
+                FittedParameterValue::JustValue(value)
+                | FittedParameterValue::FitInfo { value, .. } => *value,
+            };
+
+        suspicious_value(name, value)
+    }
+
+    /// Gives an error from `check` a context, by finding the line of the
+    /// entry it concerns.
+    fn locate(&self, err: PsruError, source: &str) -> PsruError {
+        let key = match &err {
+            PsruError::ParBadPEpoch(_) => EntryKey::Named("PEPOCH"),
+            PsruError::ParBadFrequency(_) => EntryKey::Named("F0"),
+            PsruError::BadGlitch(_, number) => {
+                EntryKey::Glitch("GLEP", *number)
+            }
+            PsruError::ParDuplicateParameters(_, items) => {
+                let Some(name) = items.first().and_then(|(_, p)| {
+                    p.split_whitespace().next().and_then(|name| {
+                        self.entries().into_iter().find(
+                            |k| matches!(k, EntryKey::Named(n) if *n == name),
+                        )
+                    })
+                }) else {
+                    return err.set_par_ctx(&ParContext::file(source));
+                };
+                name
+            }
+            _ => return err.set_par_ctx(&ParContext::file(source)),
+        };
+
+        // Repeats are reported at the last occurence, glitches at the first
+        let mut lines =
+            self.layout.lines.iter().enumerate().filter(|(_, l)| {
+                match (l.key(), &key) {
+                    (Some(EntryKey::Glitch(_, n)), EntryKey::Glitch(_, m)) => {
+                        n == m
+                    }
+                    (k, key) => k == Some(key),
+                }
+            });
+        let found = if matches!(key, EntryKey::Glitch(..)) {
+            lines.next()
+        } else {
+            lines.next_back()
+        };
+
+        let ctx = found.map_or_else(
+            || ParContext::file(source),
+            |(i, l)| ParContext::new(source, i + 1, l.text()),
+        );
+        err.set_par_ctx(&ctx)
+    }
+
+    /// Lists all entries currently held, in the order [`Parfile::write`]
+    /// would put them.
+    fn entries(&self) -> Vec<EntryKey> {
+        let mut keys = vec![EntryKey::Named("RA"), EntryKey::Named("DEC")];
+
+        keys.extend(self.parameters.iter().map(|p| EntryKey::Named(p.name())));
+        keys.extend(self.counts.iter().map(|p| EntryKey::Named(p.name())));
+        keys.extend(self.texts.iter().map(|p| EntryKey::Named(p.name())));
+        keys.extend(self.flags.iter().map(|p| EntryKey::Named(p.name())));
+        keys.extend(
+            ["TIMEEPH", "MODEL", "UNITS", "T2CMETHOD", "MODE"]
+                .into_iter()
+                .map(EntryKey::Named),
+        );
+
+        for glitch in &self.glitches {
+            keys.extend(
+                Glitch::fields()
+                    .iter()
+                    .map(|f| EntryKey::Glitch(f, glitch.number)),
+            );
+        }
+        keys.extend((0..self.jumps.len()).map(EntryKey::Jump));
+        keys.extend((0..self.extras.len()).map(EntryKey::Extra));
+
+        keys
+    }
+
+    /// Renders the values of an entry as they would be written, or `None` if
+    /// there is no such entry.
+    fn entry_tokens(&self, key: &EntryKey) -> Option<Vec<String>> {
+        fn named<T>(
+            params: &[Parameter<T>],
+            name: &str,
+            render: impl Fn(&T) -> Vec<String>,
+        ) -> Option<Vec<String>> {
+            params
+                .iter()
+                .find(|p| p.name() == name)
+                .map(|p| render(p.value()))
+        }
+        fn stated<T: std::fmt::Debug + Default + PartialEq>(
+            value: &T,
+        ) -> Option<Vec<String>> {
+            (*value != T::default()).then(|| vec![format!("{value:?}")])
+        }
+
+        let tokens = match key {
+            EntryKey::Named("RA") => self.ra.value().tokens(),
+            EntryKey::Named("DEC") => self.dec.value().tokens(),
+            EntryKey::Named("TIMEEPH") => return stated(&self.time_eph),
+            EntryKey::Named("MODEL") => return stated(&self.binary_model),
+            EntryKey::Named("UNITS") => return stated(&self.units),
+            EntryKey::Named("T2CMETHOD") => return stated(&self.t2c_method),
+            EntryKey::Named("MODE") => match self.error_mode {
+                ErrorMode::Unstated => return None,
+                ErrorMode::Mode0 => vec![String::from("0")],
+                ErrorMode::Mode1 => vec![String::from("1")],
+            },
+            EntryKey::Named(name) => {
+                return named(
+                    &self.parameters,
+                    name,
+                    FittedParameterValue::<f64>::tokens,
+                )
+                .or_else(|| named(&self.counts, name, |v| vec![v.to_string()]))
+                .or_else(|| named(&self.texts, name, |v| vec![v.clone()]))
+                .or_else(|| {
+                    named(&self.flags, name, |v| {
+                        vec![String::from(if *v { "Y" } else { "N" })]
+                    })
+                });
+            }
+            EntryKey::Glitch(field, number) => self
+                .glitches
+                .iter()
+                .find(|g| g.number == *number)
+                .and_then(|g| g.field(field))
+                .map(|v| vec![format_f64(v)])?,
+            EntryKey::Jump(index) => self.jumps.get(*index)?.tokens(),
+            EntryKey::Extra(index) => {
+                vec![self.extras.get(*index)?.value.clone()]
+            }
+        };
+
+        (!tokens.is_empty()).then_some(tokens)
+    }
+
+    /// Parses one line into the right place. Returns which entry it was, or
+    /// `None` if the key is not recognised.
+    fn parse_line(
+        &mut self,
+        line: &str,
+    ) -> Result<Option<EntryKey>, PsruError> {
         let parts = line.split_whitespace().collect::<Vec<_>>();
         if parts.len() < 2 {
-            return Err(PsruError::ParMissingValue(parts[0].to_string()));
+            // Unknown keys are left to the caller, even without a value
+            if !is_known_key(parts[0]) {
+                return Ok(None);
+            }
+            return Err(PsruError::ParMissingValue(None, parts[0].to_string()));
         }
 
-        if Glitch::parse(&parts, &mut self.glitches)? {
-            return Ok(());
+        if let Some((field, number)) =
+            Glitch::parse(&parts, &mut self.glitches)?
+        {
+            return Ok(Some(EntryKey::Glitch(field, number)));
         }
         if Jump::parse(&parts, &mut self.jumps)? {
-            return Ok(());
+            return Ok(Some(EntryKey::Jump(self.jumps.len() - 1)));
         }
-        if self.parse_special(&parts)? {
-            return Ok(());
+        if let Some(name) = self.parse_special(&parts)? {
+            return Ok(Some(EntryKey::Named(name)));
         }
 
         if let Some(flag) = parse_flag(&parts)? {
+            let name = flag.name();
             self.flags.push(flag);
-            return Ok(());
+            return Ok(Some(EntryKey::Named(name)));
         }
         if let Some(param) = parse_fitted(&parts)? {
+            let name = param.name();
             self.parameters.push(param);
-            return Ok(());
+            return Ok(Some(EntryKey::Named(name)));
         }
         if let Some(param) = parse_count(&parts)? {
+            let name = param.name();
             self.counts.push(param);
-            return Ok(());
+            return Ok(Some(EntryKey::Named(name)));
         }
-        if let Some(param) = parse_text(&parts)? {
+        if let Some(param) = parse_text(&parts) {
+            let name = param.name();
             self.texts.push(param);
-            return Ok(());
+            return Ok(Some(EntryKey::Named(name)));
         }
 
-        Ok(())
+        Ok(None)
     }
 
-    fn parse_special(&mut self, parts: &[&str]) -> Result<bool, PsruError> {
+    #[allow(clippy::too_many_lines)]
+    fn parse_special(
+        &mut self,
+        parts: &[&str],
+    ) -> Result<Option<&'static str>, PsruError> {
         let key = parts[0];
         let value = parts[1];
 
         // Coords
         if COORDS[0].1.contains(&key) {
             if *self.ra.value() != FittedParameterValue::Missing {
-                return Err(PsruError::ParRepeatParam(COORDS[0].0.to_string()));
+                return Err(PsruError::ParRepeatParam(
+                    None,
+                    COORDS[0].0.to_string(),
+                ));
             }
 
             self.ra = Parameter::new(
@@ -345,11 +787,14 @@ impl Parfile {
                 parse_coord::<RACoordType>(value, parts)?,
             );
 
-            return Ok(true);
+            return Ok(Some(COORDS[0].0));
         }
         if COORDS[1].1.contains(&key) {
             if *self.dec.value() != FittedParameterValue::Missing {
-                return Err(PsruError::ParRepeatParam(COORDS[1].0.to_string()));
+                return Err(PsruError::ParRepeatParam(
+                    None,
+                    COORDS[1].0.to_string(),
+                ));
             }
 
             self.dec = Parameter::new(
@@ -357,30 +802,37 @@ impl Parfile {
                 parse_coord::<DECCoordType>(value, parts)?,
             );
 
-            return Ok(true);
+            return Ok(Some(COORDS[1].0));
         }
 
         // Which time ephemeris to use (IF99/FB90)
         if "TIMEEPH" == key {
             if self.time_eph != TimeEphemeris::Unstated {
-                return Err(PsruError::ParRepeatParam(String::from("TIMEEPH")));
+                return Err(PsruError::ParRepeatParam(
+                    None,
+                    String::from("TIMEEPH"),
+                ));
             }
             self.time_eph = match value {
                 "IF99" => TimeEphemeris::IF99,
                 "FB90" => TimeEphemeris::FB90,
                 other => {
                     return Err(PsruError::UnknownTimeEphemeris(
+                        None,
                         other.to_string(),
                     ));
                 }
             };
-            return Ok(true);
+            return Ok(Some("TIMEEPH"));
         }
 
         // Binary model
         if "MODEL" == key {
             if self.binary_model != BinaryModel::Unstated {
-                return Err(PsruError::ParRepeatParam(String::from("MODEL")));
+                return Err(PsruError::ParRepeatParam(
+                    None,
+                    String::from("MODEL"),
+                ));
             }
             self.binary_model = match value {
                 "BT" => BinaryModel::BT,
@@ -389,74 +841,103 @@ impl Parfile {
                 "MSS" => BinaryModel::MSS,
                 other => {
                     return Err(PsruError::UnknownBinaryModel(
+                        None,
                         other.to_string(),
                     ));
                 }
             };
-            return Ok(true);
+            return Ok(Some("MODEL"));
         }
 
         // Method for transforming from terrestrial to celestial frame
         if "T2CMETHOD" == key {
             if self.t2c_method != T2CMethod::Unstated {
-                return Err(PsruError::ParRepeatParam(String::from(
-                    "T2CMETHOD",
-                )));
+                return Err(PsruError::ParRepeatParam(
+                    None,
+                    String::from("T2CMETHOD"),
+                ));
             }
             self.t2c_method = match value {
                 "TEMPO" => T2CMethod::TEMPO,
                 "IAU2000B" => T2CMethod::IAU2000B,
                 other => {
-                    return Err(PsruError::UnknownT2CMethod(other.to_string()));
+                    return Err(PsruError::UnknownT2CMethod(
+                        None,
+                        other.to_string(),
+                    ));
                 }
             };
-            return Ok(true);
+            return Ok(Some("T2CMETHOD"));
         }
 
         // Units
         if "UNITS" == key {
             if self.units != Units::Unstated {
-                return Err(PsruError::ParRepeatParam(String::from("UNITS")));
+                return Err(PsruError::ParRepeatParam(
+                    None,
+                    String::from("UNITS"),
+                ));
             }
             self.units = match value {
                 "SI" => Units::SI,
                 "TCB" => Units::TCB,
                 "TDB" => Units::TDB,
                 other => {
-                    return Err(PsruError::UnknownUnits(other.to_string()));
+                    return Err(PsruError::UnknownUnits(
+                        None,
+                        other.to_string(),
+                    ));
                 }
             };
-            return Ok(true);
+            return Ok(Some("UNITS"));
         }
 
         if "MODE" == key {
             if self.error_mode != ErrorMode::Unstated {
-                return Err(PsruError::ParRepeatParam(String::from("MODE")));
+                return Err(PsruError::ParRepeatParam(
+                    None,
+                    String::from("MODE"),
+                ));
             }
             self.error_mode = match value {
                 "0" => ErrorMode::Mode0,
                 "1" => ErrorMode::Mode1,
                 other => {
-                    return Err(PsruError::UnknownErrorMode(other.to_string()));
+                    return Err(PsruError::UnknownErrorMode(
+                        None,
+                        other.to_string(),
+                    ));
                 }
             };
-            return Ok(true);
+            return Ok(Some("MODE"));
         }
 
-        Ok(false)
+        Ok(None)
+    }
+
+    /// Removes the glitches that are not defined enough, and returns their
+    /// numbers.
+    fn drop_incomplete_glitches(&mut self) -> Vec<usize> {
+        let (complete, incomplete): (Vec<_>, Vec<_>) =
+            std::mem::take(&mut self.glitches)
+                .into_iter()
+                .partition(|g| g.check().is_ok());
+        self.glitches = complete;
+
+        incomplete.into_iter().map(|g| g.number).collect()
     }
 
     /// Performs a little check to see everything's ok.
     fn check(&self) -> Result<(), PsruError> {
         // Check mandatory params
         if !self.texts.iter().any(|t| t.name() == "PSR") {
-            return Err(PsruError::ParNoName);
+            return Err(PsruError::ParNoName(None));
         }
         self.parameters
             .iter()
             .find(|t| t.name() == "PEPOCH")
             .map_or_else(
-                || Err(PsruError::ParNoPEpoch),
+                || Err(PsruError::ParNoPEpoch(None)),
                 |p| {
                     if match *p.value() {
                         FittedParameterValue::Missing => false,
@@ -467,7 +948,7 @@ impl Parfile {
                     } {
                         Ok(())
                     } else {
-                        Err(PsruError::ParBadPEpoch)
+                        Err(PsruError::ParBadPEpoch(None))
                     }
                 },
             )?;
@@ -476,7 +957,7 @@ impl Parfile {
             .iter()
             .find(|t| t.name() == "F0")
             .map_or_else(
-                || Err(PsruError::ParNoFrequency),
+                || Err(PsruError::ParNoFrequency(None)),
                 |p| {
                     if match *p.value() {
                         FittedParameterValue::Missing => false,
@@ -487,19 +968,20 @@ impl Parfile {
                     } {
                         Ok(())
                     } else {
-                        Err(PsruError::ParBadFrequency)
+                        Err(PsruError::ParBadFrequency(None))
                     }
                 },
             )?;
 
         if !self.parameters.iter().any(|t| t.name() == "DM") {
-            return Err(PsruError::ParNoDispersion);
+            return Err(PsruError::ParNoDispersion(None));
         }
 
         // Check for duplicates
         let p64dupes = find_duplicates(&self.parameters);
         if !p64dupes.is_empty() {
             return Err(PsruError::ParDuplicateParameters(
+                None,
                 p64dupes
                     .into_iter()
                     .map(|(i, j)| {
@@ -514,13 +996,14 @@ impl Parfile {
         let ptdupes = find_duplicates(&self.texts);
         if !ptdupes.is_empty() {
             return Err(PsruError::ParDuplicateParameters(
+                None,
                 ptdupes
                     .into_iter()
                     .map(|(i, j)| {
-                        (
-                            self.parameters[i].to_string(),
-                            self.parameters[j].to_string(),
-                        )
+                        let text = |p: &Parameter<String>| {
+                            format!("{} {}", p.name(), p.value())
+                        };
+                        (text(&self.texts[i]), text(&self.texts[j]))
                     })
                     .collect(),
             ));
@@ -528,12 +1011,13 @@ impl Parfile {
         let fdupes = find_duplicates(&self.flags);
         if !fdupes.is_empty() {
             return Err(PsruError::ParDuplicateParameters(
+                None,
                 fdupes
                     .into_iter()
                     .map(|(i, j)| {
                         (
-                            self.parameters[i].name().to_string(),
-                            self.parameters[j].name().to_string(),
+                            self.flags[i].name().to_string(),
+                            self.flags[j].name().to_string(),
                         )
                     })
                     .collect(),
@@ -558,7 +1042,16 @@ fn find_duplicates<T>(params: &[Parameter<T>]) -> Vec<(usize, usize)> {
                 .iter()
                 .enumerate()
                 .find(|(_, p2)| p1.name() == p2.name())
-                .map(|(j, _)| (i, j))
+                .map(|(j, _)| (i, i + 1 + j))
         })
         .collect()
 }
+
+/// Whether a key is one that `Parfile` knows of, under any name.
+fn is_known_key(key: &str) -> bool {
+    ParameterInfo::lookup(key).is_some()
+        || COORDS.iter().any(|c| c.0 == key || c.1.contains(&key))
+        || ["TIMEEPH", "MODEL", "T2CMETHOD", "UNITS", "MODE", "JUMP"]
+            .contains(&key)
+        || Glitch::is_key(key)
+}
@@ -0,0 +1,277 @@
+//! Typed getters and setters for the general parameters of a `Parfile`.
+
+use super::{
+    FittedParameter, FittedParameterValue, Parameter, ParameterInfo,
+    ParameterKind, Parfile, parameters::is_word_list,
+};
+use crate::error::PsruError;
+
+type Result<T> = std::result::Result<T, PsruError>;
+
+impl Parfile {
+    /// Finds a double parameter by its name or alias.
+    pub fn parameter(&self, key: &str) -> Option<&FittedParameter> {
+        let name = ParameterInfo::lookup(key)?.name;
+        self.parameters.iter().find(|p| p.name() == name)
+    }
+
+    /// The value of a double parameter, found by its name or alias.
+    pub fn value(&self, key: &str) -> Option<f64> {
+        match self.parameter(key)?.value() {
+            FittedParameterValue::Missing => None,
+            FittedParameterValue::JustValue(value)
+            | FittedParameterValue::FitInfo { value, .. } => Some(*value),
+        }
+    }
+
+    /// The value of an integer parameter, found by its name or alias.
+    pub fn count(&self, key: &str) -> Option<u32> {
+        let name = ParameterInfo::lookup(key)?.name;
+        self.counts
+            .iter()
+            .find(|p| p.name() == name)
+            .map(|p| *p.value())
+    }
+
+    /// The value of a text parameter, found by its name or alias.
+    pub fn text(&self, key: &str) -> Option<&str> {
+        let name = ParameterInfo::lookup(key)?.name;
+        self.texts
+            .iter()
+            .find(|p| p.name() == name)
+            .map(|p| p.value().as_str())
+    }
+
+    /// The value of a flag, found by its name or alias.
+    pub fn flag(&self, key: &str) -> Option<bool> {
+        let name = ParameterInfo::lookup(key)?.name;
+        self.flags
+            .iter()
+            .find(|p| p.name() == name)
+            .map(|p| *p.value())
+    }
+
+    /// Sets the value of a double parameter, adding it if not present. Any
+    /// fit info is kept.
+    ///
+    /// # Errors
+    /// Fails if the key is not a known double parameter, if the value is not
+    /// finite, or if it is out of bounds for `F0` or `PEPOCH`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use psrutils::parfile::Parfile;
+    /// # fn test() -> Result<(), psrutils::error::PsruError> {
+    /// let par_text = "
+    ///     PSR    J0000-9999\n\
+    ///     PEPOCH 55000\n\
+    ///     F0     9001 1 0.0001\n\
+    ///     DM     1001.1
+    /// ".as_bytes();
+    /// let mut par = Parfile::read(std::io::BufReader::new(par_text))?;
+    ///
+    /// par.set_value("F2", 1e-25)?;
+    /// par.set_value("DM", 1002.2)?;
+    /// par.set_fit("DM", true)?;
+    ///
+    /// assert_eq!(par.value("F2"), Some(1e-25));
+    /// assert!(par.set_value("F0", -1.0).is_err());
+    /// assert!(par.set_value("PEPOCH", f64::NAN).is_err());
+    /// assert!(par.set_value("NITS", 1.0).is_err());
+    /// # Ok(())
+    /// # }
+    /// # test().unwrap();
+    /// ```
+    pub fn set_value(&mut self, key: &str, value: f64) -> Result<()> {
+        let info = lookup_kind(key, ParameterKind::Double)?;
+        if !value.is_finite() {
+            return Err(PsruError::unparsable(&value.to_string(), "double"));
+        }
+        match info.name {
+            "F0" if value <= 0.0 => {
+                return Err(PsruError::ParBadFrequency(None));
+            }
+            "PEPOCH" if value <= 0.0 => {
+                return Err(PsruError::ParBadPEpoch(None));
+            }
+            _ => {}
+        }
+
+        let Some(param) =
+            self.parameters.iter_mut().find(|p| p.name() == info.name)
+        else {
+            self.parameters.push(Parameter::new(
+                &info.data(),
+                FittedParameterValue::JustValue(value),
+            ));
+            return Ok(());
+        };
+
+        let current = param.value_mut();
+        match current {
+            FittedParameterValue::Missing => {
+                *current = FittedParameterValue::JustValue(value);
+            }
+            FittedParameterValue::JustValue(v)
+            | FittedParameterValue::FitInfo { value: v, .. } => *v = value,
+        }
+
+        Ok(())
+    }
+
+    /// Sets whether to fit a double parameter.
+    ///
+    /// # Errors
+    /// Fails if the key is not a known double parameter, or if it has no
+    /// value.
+    pub fn set_fit(&mut self, key: &str, fit: bool) -> Result<()> {
+        self.update_fit_info(key, |f, _| *f = fit)
+    }
+
+    /// Sets the uncertainty of a double parameter.
+    ///
+    /// # Errors
+    /// Fails if the key is not a known double parameter, if it has no value,
+    /// or if the uncertainty is negative or not finite.
+    pub fn set_uncertainty(&mut self, key: &str, error: f64) -> Result<()> {
+        if error.is_sign_negative() || !error.is_finite() {
+            return Err(PsruError::unparsable(
+                &error.to_string(),
+                "finite, non-negative uncertainty",
+            ));
+        }
+
+        self.update_fit_info(key, |_, e| *e = error)
+    }
+
+    /// Sets the value of an integer parameter, adding it if not present.
+    ///
+    /// # Errors
+    /// Fails if the key is not a known integer parameter.
+    pub fn set_count(&mut self, key: &str, value: u32) -> Result<()> {
+        let info = lookup_kind(key, ParameterKind::Count)?;
+        set_or_push(&mut self.counts, &info, value);
+        Ok(())
+    }
+
+    /// Sets the value of a text parameter, adding it if not present. Most
+    /// take a single word, but lists such as `CLK_CORR_CHAIN` take any
+    /// number of words, which are stored separated by single spaces.
+    ///
+    /// # Errors
+    /// Fails if the key is not a known text parameter, or the value has no
+    /// words, or more than one where a single word is expected.
+    pub fn set_text(&mut self, key: &str, value: &str) -> Result<()> {
+        let info = lookup_kind(key, ParameterKind::Text)?;
+        let words = value.split_whitespace().collect::<Vec<_>>();
+        if is_word_list(info.name) {
+            if words.is_empty() {
+                return Err(PsruError::unparsable(value, "list of words"));
+            }
+        } else if words.len() != 1 || words[0] != value {
+            return Err(PsruError::unparsable(value, "single word"));
+        }
+
+        set_or_push(&mut self.texts, &info, words.join(" "));
+        Ok(())
+    }
+
+    /// Sets a flag, adding it if not present.
+    ///
+    /// # Errors
+    /// Fails if the key is not a known flag.
+    pub fn set_flag(&mut self, key: &str, value: bool) -> Result<()> {
+        let info = lookup_kind(key, ParameterKind::Flag)?;
+        set_or_push(&mut self.flags, &info, value);
+        Ok(())
+    }
+
+    /// Removes a parameter from the general collections, by its name or
+    /// alias. Returns whether it was present.
+    ///
+    /// # Errors
+    /// Fails if the key is not known, or if the parameter is mandatory (i.e.
+    /// `PSR`, `F0`, `PEPOCH`, or `DM`).
+    pub fn remove(&mut self, key: &str) -> Result<bool> {
+        let info = ParameterInfo::lookup(key).ok_or_else(|| {
+            PsruError::ParUnrecognisedKey(None, key.to_string())
+        })?;
+
+        match info.name {
+            "PSR" => return Err(PsruError::ParNoName(None)),
+            "F0" => return Err(PsruError::ParNoFrequency(None)),
+            "PEPOCH" => return Err(PsruError::ParNoPEpoch(None)),
+            "DM" => return Err(PsruError::ParNoDispersion(None)),
+            _ => {}
+        }
+
+        let name = info.name;
+        let before = self.parameters.len()
+            + self.counts.len()
+            + self.texts.len()
+            + self.flags.len();
+
+        self.parameters.retain(|p| p.name() != name);
+        self.counts.retain(|p| p.name() != name);
+        self.texts.retain(|p| p.name() != name);
+        self.flags.retain(|p| p.name() != name);
+
+        let after = self.parameters.len()
+            + self.counts.len()
+            + self.texts.len()
+            + self.flags.len();
+
+        Ok(before != after)
+    }
+
+    fn update_fit_info(
+        &mut self,
+        key: &str,
+        update: impl FnOnce(&mut bool, &mut f64),
+    ) -> Result<()> {
+        let info = lookup_kind(key, ParameterKind::Double)?;
+        let param = self
+            .parameters
+            .iter_mut()
+            .find(|p| p.name() == info.name)
+            .ok_or_else(|| {
+                PsruError::ParMissingValue(None, info.name.to_string())
+            })?;
+
+        let current = param.value_mut();
+        let (mut fit, mut error, value) = match current {
+            FittedParameterValue::Missing => {
+                return Err(PsruError::ParMissingValue(
+                    None,
+                    info.name.to_string(),
+                ));
+            }
+            FittedParameterValue::JustValue(value) => (false, 0.0, *value),
+            FittedParameterValue::FitInfo { value, fit, error } => {
+                (*fit, *error, *value)
+            }
+        };
+
+        update(&mut fit, &mut error);
+        *current = FittedParameterValue::FitInfo { value, fit, error };
+
+        Ok(())
+    }
+}
+
+fn lookup_kind(key: &str, kind: ParameterKind) -> Result<ParameterInfo> {
+    ParameterInfo::lookup(key)
+        .filter(|info| info.kind == kind)
+        .ok_or_else(|| PsruError::ParUnrecognisedKey(None, key.to_string()))
+}
+
+fn set_or_push<T>(
+    params: &mut Vec<Parameter<T>>,
+    info: &ParameterInfo,
+    value: T,
+) {
+    match params.iter_mut().find(|p| p.name() == info.name) {
+        Some(param) => *param.value_mut() = value,
+        None => params.push(Parameter::new(&info.data(), value)),
+    }
+}
@@ -1,5 +1,5 @@
 use super::PsruError;
-use crate::parse_tools::parse_f64;
+use crate::parse_tools::{format_f64, parse_f64};
 
 /// The data representing a glitch. The index, `number`, is kept as-is
 /// from the source, but it should be noted that the reader expects a
@@ -7,9 +7,10 @@ use crate::parse_tools::parse_f64;
 ///
 /// If e.g. `GLF1_2` shows up, we assume there should also be a glitch 1
 /// before it, but perhaps written later, so we presumptiously add it. If
-/// there is no glitch 1, that produces a warning in the end of the `read`
-/// function. If there is not enough data to fully define a glitch, it is
-/// removed and a warning is issued.
+/// there is not enough data to fully define a glitch, including one that
+/// was never given, that is an error. In lenient mode (see
+/// [`Parfile::read_with`](super::Parfile::read_with)), the glitch is
+/// instead removed and a warning is issued.
 #[derive(Debug, Default, Clone)]
 pub struct Glitch {
     /// The index used in the file
@@ -28,47 +29,52 @@ pub struct Glitch {
     pub td: f64,
 }
 
+/// The keys of all glitch fields, without indices.
+const FIELDS: [&str; 6] = ["GLEP", "GLPH", "GLF0", "GLF1", "GLF0D", "GLTD"];
+
 impl Glitch {
     /// This will parse one glitch parameter, since there does not seem to be
     /// any restrictions on where these paramaters may occur in the file.
+    ///
+    /// Returns the field and glitch index that was read, if it was a glitch.
     pub(crate) fn parse(
         parts: &[&str],
         glitches: &mut Vec<Self>,
-    ) -> Result<bool, PsruError> {
+    ) -> Result<Option<(&'static str, usize)>, PsruError> {
         if parts.len() != 2 {
-            return Ok(false);
+            return Ok(None);
         }
         let value = parts[1];
 
         let p0ps = parts[0].split('_').collect::<Vec<_>>();
         if p0ps.len() != 2 {
-            return Ok(false);
+            return Ok(None);
         }
         let key = p0ps[0].to_uppercase();
 
-        if !["GLEP", "GLPH", "GLF0", "GLF1", "GLF0D", "GLTD"]
-            .contains(&key.as_str())
-        {
-            return Ok(false);
-        }
+        let Some(field) = FIELDS.iter().find(|f| **f == key) else {
+            return Ok(None);
+        };
 
         let index = p0ps[1];
-
-        let index =
-            index.parse::<usize>().map_err(|_| PsruError::Unparsable {
-                value: index.to_string(),
-                to_type: "glitch index",
-            })?;
+        let number = index
+            .parse::<usize>()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| PsruError::unparsable(index, "glitch index"))?;
         let value = parse_f64(value)?;
 
         // Make sure there are glitches for all indicated slots...
-        while glitches.len() <= index {
-            glitches.push(Self::default());
+        while glitches.len() < number {
+            glitches.push(Self {
+                number: glitches.len() + 1,
+                ..Default::default()
+            });
         }
 
-        let glitch = glitches.get_mut(index).unwrap();
+        let glitch = glitches.get_mut(number - 1).unwrap();
 
-        match key.as_str() {
+        match *field {
             "GLEP" => glitch.epoch = value,
             "GLPH" => glitch.phase = value,
             "GLF0" => glitch.f0 = value,
@@ -79,13 +85,38 @@ impl Glitch {
             _ => unreachable!(),
         }
 
-        Ok(true)
+        Ok(Some((field, number)))
+    }
+
+    /// The value of a field, by its key without index (e.g. `GLEP`).
+    pub(crate) fn field(&self, field: &str) -> Option<f64> {
+        match field {
+            "GLEP" => Some(self.epoch),
+            "GLPH" => Some(self.phase),
+            "GLF0" => Some(self.f0),
+            "GLF1" => Some(self.f1),
+            "GLF0D" => Some(self.f0d),
+            "GLTD" => Some(self.td),
+            _ => None,
+        }
+    }
+
+    /// Whether a key is a glitch field, e.g. `GLEP_1`.
+    pub(crate) fn is_key(key: &str) -> bool {
+        key.split_once('_').is_some_and(|(field, _)| {
+            FIELDS.contains(&field.to_uppercase().as_str())
+        })
+    }
+
+    /// The keys of all fields, without indices.
+    pub(crate) const fn fields() -> &'static [&'static str] {
+        &FIELDS
     }
 
     /// Checks if the glitch is defined enough.
     pub(crate) fn check(&self) -> Result<(), PsruError> {
         if self.f0 == 0.0 || self.f0d == 0.0 || self.epoch == 0.0 {
-            return Err(PsruError::BadGlitch(self.number));
+            return Err(PsruError::BadGlitch(None, self.number));
         }
 
         Ok(())
@@ -101,12 +132,12 @@ impl Glitch {
             GLF0D_{0} {5}\n\
             GLTD_{0}  {6}\n",
             self.number,
-            self.epoch,
-            self.phase,
-            self.f0,
-            self.f1,
-            self.f0d,
-            self.td,
+            format_f64(self.epoch),
+            format_f64(self.phase),
+            format_f64(self.f0),
+            format_f64(self.f1),
+            format_f64(self.f0d),
+            format_f64(self.td),
         )
     }
 }
@@ -1,9 +1,21 @@
-use super::PsruError;
+use super::{Parfile, PsruError};
 use crate::parse_tools::parse_bool;
-use crate::parse_tools::parse_f64;
+use crate::parse_tools::{format_f64, parse_f64};
+use crate::timfile::{Condition, TOAInfo};
 
 /// Add a constant oﬀset between specified TOAs.
-#[derive(Debug)]
+///
+/// Jumps are written as `JUMP`, a selector, and then optionally the value,
+/// the fit flag, and the uncertainty, as Tempo2 and PINT write them:
+///
+/// ```text
+/// JUMP MJD 55000 55100 0.001 1
+/// JUMP -fe L-wide 0.0 1 1e-6
+/// JUMP -sys X
+/// ```
+///
+/// A missing value is 0, and a missing fit flag means no fitting.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Jump {
     /// What flag was used to id the jump arguments.
     pub jtype: JumpType,
@@ -13,20 +25,47 @@ pub struct Jump {
 
     /// Fitting status.
     pub fit: bool,
+
+    /// The uncertainty of the value, if it has been fitted.
+    pub error: Option<f64>,
 }
 
-#[derive(Debug)]
+/// Which TOAs a jump applies to.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum JumpType {
     Mjd(f64, f64),
     Freq(f64, f64),
     Tel(String),
     Name(String),
+    /// The flag, with its `-`, and its value.
     Flag(String, String),
 }
+impl JumpType {
+    /// The same selection as a [`Condition`] on TOAs.
+    pub fn condition(&self) -> Condition {
+        match self {
+            Self::Mjd(min, max) => Condition::Mjd(*min, *max),
+            Self::Freq(min, max) => Condition::Freq(*min, *max),
+            Self::Tel(site) => Condition::Tel(site.clone()),
+            Self::Name(name) => Condition::Name(name.clone()),
+            Self::Flag(flag, value) => Condition::Flag(
+                flag.strip_prefix('-').unwrap_or(flag).to_string(),
+                value.clone(),
+            ),
+        }
+    }
+
+    /// Whether the jump applies to the TOA.
+    pub fn matches(&self, toa: &TOAInfo) -> bool {
+        self.condition().matches(toa)
+    }
+}
 
 impl Jump {
     /// This will parse a jump, which are written on one line. If anything is
-    /// missing or malformed, an error is returned.
+    /// missing or malformed, an error is returned. The selector keywords may
+    /// be written in any case.
     pub(crate) fn parse(
         parts: &[&str],
         jumps: &mut Vec<Self>,
@@ -36,58 +75,211 @@ impl Jump {
         }
 
         let es = parts.join(" ");
-        let error = || PsruError::IncompleteJump(es.clone());
-        if parts.len() < 3 {
-            return Err(error());
-        }
+        let incomplete = || PsruError::IncompleteJump(None, es.clone());
+        let Some(selector) = parts.get(1) else {
+            return Err(incomplete());
+        };
 
-        let mut parts = parts.iter();
-        _ = parts.next();
-        let selector = match *parts.next().unwrap() {
-            "MJD" => JumpType::Mjd(
-                parse_f64(parts.next().ok_or_else(error)?)?,
-                parse_f64(parts.next().ok_or_else(error)?)?,
-            ),
-            "FREQ" => JumpType::Freq(
-                parse_f64(parts.next().ok_or_else(error)?)?,
-                parse_f64(parts.next().ok_or_else(error)?)?,
-            ),
-            "TEL" => {
-                JumpType::Tel((*parts.next().ok_or_else(error)?).to_string())
-            }
-            "NAME" => {
-                JumpType::Name((*parts.next().ok_or_else(error)?).to_string())
-            }
+        let (jtype, rest) =
+            match (selector.to_ascii_uppercase().as_str(), &parts[2..]) {
+                ("MJD", [min, max, rest @ ..]) => {
+                    (JumpType::Mjd(parse_f64(min)?, parse_f64(max)?), rest)
+                }
+                ("FREQ", [min, max, rest @ ..]) => {
+                    (JumpType::Freq(parse_f64(min)?, parse_f64(max)?), rest)
+                }
+                ("TEL", [site, rest @ ..]) => {
+                    (JumpType::Tel((*site).to_string()), rest)
+                }
+                ("NAME", [name, rest @ ..]) => {
+                    (JumpType::Name((*name).to_string()), rest)
+                }
+                ("MJD" | "FREQ" | "TEL" | "NAME", _) => {
+                    return Err(incomplete());
+                }
+                (_, [value, rest @ ..]) => (
+                    JumpType::Flag(
+                        (*selector).to_string(),
+                        (*value).to_string(),
+                    ),
+                    rest,
+                ),
+                (_, []) => return Err(incomplete()),
+            };
 
-            flag => JumpType::Flag(
-                flag.to_string(),
-                (*parts.next().ok_or_else(error)?).to_string(),
-            ),
+        let (value, fit, error) = match rest {
+            [] => (0.0, false, None),
+            [value] => (parse_f64(value)?, false, None),
+            [value, fit] => (parse_f64(value)?, parse_bool(fit)?, None),
+            [value, fit, error] => {
+                (parse_f64(value)?, parse_bool(fit)?, Some(parse_f64(error)?))
+            }
+            _ => return Err(incomplete()),
         };
 
-        let jump = Self {
-            jtype: selector,
-            value: parse_f64(parts.next().ok_or_else(error)?)?,
-            fit: parse_bool(parts.next().ok_or_else(error)?)?,
-        };
-        jumps.push(jump);
+        jumps.push(Self {
+            jtype,
+            value,
+            fit,
+            error,
+        });
 
         Ok(true)
     }
 
+    /// Everything following the `JUMP` key, as separate tokens. The value
+    /// and uncertainty are written as by [`format_f64`], so very small ones
+    /// take the exponent form, while the bounds of MJD and frequency ranges
+    /// are always written plainly.
+    pub(crate) fn tokens(&self) -> Vec<String> {
+        let mut tokens = match &self.jtype {
+            JumpType::Mjd(v1, v2) => {
+                vec![String::from("MJD"), v1.to_string(), v2.to_string()]
+            }
+            JumpType::Freq(v1, v2) => {
+                vec![String::from("FREQ"), v1.to_string(), v2.to_string()]
+            }
+            JumpType::Tel(id) => vec![String::from("TEL"), id.clone()],
+            JumpType::Name(name) => vec![String::from("NAME"), name.clone()],
+            JumpType::Flag(f, v) => vec![f.clone(), v.clone()],
+        };
+
+        tokens.push(format_f64(self.value));
+        tokens.push(String::from(if self.fit { "1" } else { "0" }));
+        if let Some(error) = self.error {
+            tokens.push(format_f64(error));
+        }
+
+        tokens
+    }
+
+    /// The jump as a line of a `.par` file.
     pub(crate) fn write(&self) -> String {
-        let mut line = String::from("JUMP");
-        match &self.jtype {
-            JumpType::Mjd(v1, v2) => line += &format!("MJD {v1} {v2}"),
-            JumpType::Freq(v1, v2) => line += &format!("FREQ {v1} {v2}"),
-            JumpType::Tel(id) => line += &format!("TEL {id}"),
-            JumpType::Name(name) => line += &format!("NAME {name}"),
-            JumpType::Flag(f, v) => line += &format!("{f} {v}"),
+        format!("JUMP {}\n", self.tokens().join(" "))
+    }
+}
+
+impl Parfile {
+    /// Finds which of the TOAs each jump applies to.
+    ///
+    /// ```
+    /// # use psrutils::parfile::Parfile;
+    /// # use psrutils::timfile::TOAInfo;
+    /// let par = "\
+    /// PSR J0000-9999
+    /// PEPOCH 55000
+    /// F0 9001
+    /// DM 10
+    /// JUMP -fe 430 0.001 1
+    /// JUMP -fe 820 0.002 1
+    /// ";
+    /// let par = Parfile::read(par.as_bytes()).unwrap();
+    /// let toas = [
+    ///     "a 1400 55000.5 1.0 ao -fe L-wide",
+    ///     "a 430 55001.5 1.0 ao -fe 430",
+    /// ]
+    /// .map(|l| TOAInfo::from_line_tempo2(l).unwrap());
+    ///
+    /// let selection = par.jump_selection(&toas);
+    /// assert_eq!(selection.toas(0), [1]);
+    /// assert_eq!(selection.empty(), [1]);
+    /// assert_eq!(selection.reference(), [0]);
+    /// assert_eq!(selection.offsets(), [0.0, 0.001]);
+    /// ```
+    pub fn jump_selection(&self, toas: &[TOAInfo]) -> JumpSelection {
+        let mut by_jump = Vec::with_capacity(self.jumps.len());
+        let mut by_toa = vec![Vec::new(); toas.len()];
+
+        for (j, jump) in self.jumps.iter().enumerate() {
+            let condition = jump.jtype.condition();
+            let selected = toas
+                .iter()
+                .enumerate()
+                .filter(|(_, toa)| condition.matches(toa))
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>();
+
+            for i in &selected {
+                by_toa[*i].push(j);
+            }
+            by_jump.push(selected);
+        }
+
+        JumpSelection {
+            by_jump,
+            by_toa,
+            values: self.jumps.iter().map(|j| j.value).collect(),
         }
+    }
+}
 
-        line +=
-            &format!(" {} {}", self.value, if self.fit { "1" } else { "0" });
+/// Which TOAs the jumps of a `.par` file apply to, and the other way
+/// around. Made by [`Parfile::jump_selection`].
+///
+/// Jumps and TOAs are referred to by their indices, in [`Parfile::jumps`]
+/// and in the TOAs given.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JumpSelection {
+    by_jump: Vec<Vec<usize>>,
+    by_toa: Vec<Vec<usize>>,
+    values: Vec<f64>,
+}
+impl JumpSelection {
+    /// The TOAs a jump applies to, in order.
+    ///
+    /// # Panics
+    /// `panic`s if there is no jump with the index.
+    pub fn toas(&self, jump: usize) -> &[usize] {
+        &self.by_jump[jump]
+    }
+
+    /// The jumps that apply to a TOA, in order.
+    ///
+    /// # Panics
+    /// `panic`s if there is no TOA with the index.
+    pub fn jumps(&self, toa: usize) -> &[usize] {
+        &self.by_toa[toa]
+    }
+
+    /// The jumps that apply to no TOAs, which can not be fitted.
+    pub fn empty(&self) -> Vec<usize> {
+        (0..self.by_jump.len())
+            .filter(|j| self.by_jump[*j].is_empty())
+            .collect()
+    }
+
+    /// The pairs of jumps that apply to some of the same TOAs, whose values
+    /// then add up for those TOAs.
+    pub fn overlaps(&self) -> Vec<(usize, usize)> {
+        let mut pairs = self
+            .by_toa
+            .iter()
+            .flat_map(|jumps| {
+                jumps.iter().enumerate().flat_map(move |(n, first)| {
+                    jumps[n + 1..].iter().map(move |second| (*first, *second))
+                })
+            })
+            .collect::<Vec<_>>();
+
+        pairs.sort_unstable();
+        pairs.dedup();
+        pairs
+    }
+
+    /// The TOAs that no jump applies to. These make up the reference group,
+    /// which the jumps are measured against.
+    pub fn reference(&self) -> Vec<usize> {
+        (0..self.by_toa.len())
+            .filter(|i| self.by_toa[*i].is_empty())
+            .collect()
+    }
 
-        line
+    /// The time offset (s) of each TOA, which is the sum of the values of
+    /// the jumps that apply to it. It is 0 for the reference group.
+    pub fn offsets(&self) -> Vec<f64> {
+        self.by_toa
+            .iter()
+            .map(|jumps| jumps.iter().fold(0.0, |sum, j| sum + self.values[*j]))
+            .collect()
     }
 }
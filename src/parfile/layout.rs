@@ -0,0 +1,170 @@
+//! Bookkeeping of the original text of a `.par` file, so that it can be
+//! written back out with as few changes as possible.
+
+/// Identifies which value of a `Parfile` a line represents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum EntryKey {
+    /// A parameter with a unique name, e.g. `F0`, `RA`, or `TIMEEPH`.
+    Named(&'static str),
+    /// One field of a glitch, e.g. `GLEP` of glitch 1.
+    Glitch(&'static str, usize),
+    /// A jump, by its index.
+    Jump(usize),
+    /// An unrecognised parameter, by its index.
+    Extra(usize),
+}
+impl EntryKey {
+    /// The key to use when there is no original line to take it from.
+    pub(super) fn canonical(&self) -> String {
+        match self {
+            Self::Named(name) => (*name).to_string(),
+            Self::Glitch(field, number) => format!("{field}_{number}"),
+            Self::Jump(_) => String::from("JUMP"),
+            // These carry their own keys
+            Self::Extra(_) => String::new(),
+        }
+    }
+}
+
+/// The lines of a `.par` file as they were read, along with what they meant
+/// at the time, so that [`Parfile::write_lossless`] can keep them. A
+/// `Parfile` built by hand has an empty layout.
+///
+/// [`Parfile::write_lossless`]: super::Parfile::write_lossless
+#[derive(Debug, Clone, Default)]
+pub struct Layout {
+    pub(super) lines: Vec<SourceLine>,
+}
+
+/// A line as it was read, along with what it meant at the time.
+#[derive(Debug, Clone)]
+pub(super) struct SourceLine {
+    text: String,
+    entry: Option<(EntryKey, Vec<String>)>,
+}
+impl SourceLine {
+    /// A line that does not represent any value, i.e. a comment or a blank.
+    pub(super) const fn verbatim(text: String) -> Self {
+        Self { text, entry: None }
+    }
+
+    /// A line representing an entry, whose values were rendered as `tokens`
+    /// right after reading it.
+    pub(super) const fn entry(
+        text: String,
+        key: EntryKey,
+        tokens: Vec<String>,
+    ) -> Self {
+        Self {
+            text,
+            entry: Some((key, tokens)),
+        }
+    }
+
+    pub(super) fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub(super) const fn key(&self) -> Option<&EntryKey> {
+        match &self.entry {
+            Some((key, _)) => Some(key),
+            None => None,
+        }
+    }
+
+    /// Produces the text to write for this line, given the current tokens of
+    /// its entry. If nothing has changed, the original text is returned.
+    /// If the entry has been removed, there's nothing to write.
+    pub(super) fn render(&self, current: Option<&[String]>) -> Option<String> {
+        let Some((_, original)) = &self.entry else {
+            return Some(self.text.clone());
+        };
+        let current = current?;
+
+        if original.as_slice() == current {
+            return Some(self.text.clone());
+        }
+
+        Some(self.realign(current))
+    }
+
+    /// Writes new tokens into the columns used by the original line, keeping
+    /// the key as it was spelled (e.g. `PSRJ` instead of `PSR`), and any
+    /// trailing comment.
+    fn realign(&self, tokens: &[String]) -> String {
+        let chars = self.text.chars().collect::<Vec<_>>();
+        let spans = token_spans(&self.text);
+        let end = spans
+            .iter()
+            .skip(1)
+            .position(|s| chars[s.0] == '#')
+            .map_or(spans.len(), |i| i + 1);
+        let (spans, comment) = spans.split_at(end);
+        let mut line = chars[..spans[0].1].iter().collect::<String>();
+
+        for (i, token) in tokens.iter().enumerate() {
+            pad_to(&mut line, spans.get(i + 1).map_or(0, |s| s.0));
+            line += token;
+        }
+        if let Some((start, _)) = comment.first() {
+            pad_to(&mut line, *start);
+            line.extend(&chars[*start..]);
+        }
+
+        line
+    }
+}
+
+/// Pads a line with spaces up to a (char) column, or with a single space if
+/// it already reaches it.
+fn pad_to(line: &mut String, column: usize) {
+    let width = line.chars().count();
+    let pad = if column > width { column - width } else { 1 };
+    line.extend(std::iter::repeat_n(' ', pad));
+}
+
+/// Finds the (char) start and end of each whitespace-separated token.
+fn token_spans(text: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut start = None;
+
+    for (i, c) in text.chars().enumerate() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some(i),
+            (true, Some(s)) => {
+                spans.push((s, i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        spans.push((s, text.chars().count()));
+    }
+
+    spans
+}
+
+/// Whether the line holds nothing of value, i.e. is blank or a comment.
+pub(super) fn is_verbatim(line: &str) -> bool {
+    let trimmed = line.trim_start();
+
+    trimmed.is_empty()
+        || trimmed.starts_with('#')
+        || trimmed == "C"
+        || trimmed.starts_with("C ")
+        || trimmed.starts_with("C\t")
+}
+
+/// The part of a line before any trailing comment, e.g. `# set by hand`.
+pub(super) fn strip_comment(line: &str) -> &str {
+    let mut previous = ' ';
+    for (i, c) in line.char_indices() {
+        if c == '#' && previous.is_whitespace() {
+            return line[..i].trim_end();
+        }
+        previous = c;
+    }
+
+    line
+}
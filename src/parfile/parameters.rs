@@ -2,7 +2,7 @@ use std::str::FromStr;
 
 use crate::data_types::J2000Coord;
 use crate::error::PsruError;
-use crate::parse_tools::{parse_bool, parse_f64, parse_u32};
+use crate::parse_tools::{format_f64, parse_bool, parse_f64, parse_u32};
 
 type Result<T> = std::result::Result<T, PsruError>;
 
@@ -26,6 +26,34 @@ pub enum FittedParameterValue<T> {
     },
 }
 
+impl<T> FittedParameterValue<T> {
+    /// The value and any fit info, as they would be written, with the value
+    /// itself written by `render`.
+    fn tokens_with(&self, render: impl Fn(&T) -> String) -> Vec<String> {
+        match self {
+            Self::Missing => Vec::new(),
+            Self::JustValue(value) => vec![render(value)],
+            Self::FitInfo { value, fit, error } => vec![
+                render(value),
+                String::from(if *fit { "1" } else { "0" }),
+                format_f64(*error),
+            ],
+        }
+    }
+}
+impl FittedParameterValue<f64> {
+    /// The value and any fit info, as they would be written.
+    pub(crate) fn tokens(&self) -> Vec<String> {
+        self.tokens_with(|value| format_f64(*value))
+    }
+}
+impl<T> FittedParameterValue<J2000Coord<T>> {
+    /// The value and any fit info, as they would be written.
+    pub(crate) fn tokens(&self) -> Vec<String> {
+        self.tokens_with(ToString::to_string)
+    }
+}
+
 #[derive(Debug, Default)]
 /// An entry in a `.par` file.
 pub struct Parameter<T> {
@@ -36,7 +64,7 @@ pub struct Parameter<T> {
 impl<T> Parameter<T> {
     /// The name of the parameter, i.e. a functioning key, and not necessarily
     /// the most readable thing.
-    pub const fn name(&self) -> &str {
+    pub const fn name(&self) -> &'static str {
         self.name
     }
 
@@ -51,6 +79,10 @@ impl<T> Parameter<T> {
         &self.value
     }
 
+    pub(crate) const fn value_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+
     pub(crate) const fn new(
         data: &(&'static str, &[&str], &'static str),
         value: T,
@@ -62,25 +94,26 @@ impl<T> Parameter<T> {
         }
     }
 }
-impl<T> std::fmt::Display for Parameter<FittedParameterValue<T>>
-where
-    T: std::fmt::Display,
-{
+impl std::fmt::Display for FittedParameter {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match &self.value {
-            FittedParameterValue::Missing => write!(f, "MISSING"),
-            FittedParameterValue::FitInfo { value, fit, error } => write!(
-                f,
-                "{} {} {} {}",
-                self.name,
-                value,
-                if *fit { "1" } else { "0" },
-                error,
-            ),
-            FittedParameterValue::JustValue(v) => {
-                write!(f, "{} {}", self.name, v)
-            }
-        }
+        write_fitted(f, self.name, &self.value.tokens())
+    }
+}
+impl<T> std::fmt::Display for Parameter<J2000Fit<T>> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_fitted(f, self.name, &self.value.tokens())
+    }
+}
+
+fn write_fitted(
+    f: &mut std::fmt::Formatter<'_>,
+    name: &str,
+    tokens: &[String],
+) -> std::fmt::Result {
+    if tokens.is_empty() {
+        write!(f, "MISSING")
+    } else {
+        write!(f, "{name} {}", tokens.join(" "))
     }
 }
 
@@ -174,17 +207,125 @@ pub(super) fn parse_count(parts: &[&str]) -> Result<Option<Parameter<u32>>> {
     Ok(None)
 }
 
-pub(super) fn parse_text(parts: &[&str]) -> Result<Option<Parameter<String>>> {
+/// Text parameters that hold a list of words, rather than a single one.
+const WORD_LISTS: &[&str] = &["CLK_CORR_CHAIN"];
+
+/// Whether the text parameter `name` holds a list of words, which are kept
+/// separated by single spaces.
+pub(super) fn is_word_list(name: &str) -> bool {
+    WORD_LISTS.contains(&name)
+}
+
+pub(super) fn parse_text(parts: &[&str]) -> Option<Parameter<String>> {
     let key = parts[0];
-    let value = parts[1];
 
     TEXTS
         .iter()
         .find(|t| t.0 == key || t.1.contains(&key))
-        .map(|data| Some(Parameter::new(data, value.to_string())))
-        .ok_or_else(|| PsruError::ParUnrecognisedKey(key.to_string()))
+        .map(|data| {
+            let value = if is_word_list(data.0) {
+                parts[1..].join(" ")
+            } else {
+                parts[1].to_string()
+            };
+            Parameter::new(data, value)
+        })
+}
+
+/// Which kind of value a parameter holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParameterKind {
+    /// A double, possibly with fit info. See [`FittedParameterValue`].
+    Double,
+    /// An integer.
+    Count,
+    /// Text.
+    Text,
+    /// A boolean flag.
+    Flag,
+}
+
+/// What is known about a parameter key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParameterInfo {
+    /// The name used for the parameter.
+    pub name: &'static str,
+    /// Other names the parameter may be given.
+    pub aliases: &'static [&'static str],
+    /// A description, if there is one.
+    pub description: &'static str,
+    /// Which kind of value it holds.
+    pub kind: ParameterKind,
+}
+impl ParameterInfo {
+    /// Looks up a parameter by its name or any alias. Only parameters that
+    /// are stored in the general collections of a `Parfile` are found, i.e.
+    /// not coordinates, glitches, jumps, or the ones with their own enums.
+    ///
+    /// ```
+    /// # use psrutils::parfile::{ParameterInfo, ParameterKind};
+    /// let info = ParameterInfo::lookup("PSRJ").unwrap();
+    /// assert_eq!(info.name, "PSR");
+    /// assert_eq!(info.kind, ParameterKind::Text);
+    ///
+    /// assert!(ParameterInfo::lookup("F99").is_none());
+    /// ```
+    pub fn lookup(key: &str) -> Option<Self> {
+        [
+            (PARAMETERS, ParameterKind::Double),
+            (PARAMETERS_U32, ParameterKind::Count),
+            (TEXTS, ParameterKind::Text),
+            (FLAGS, ParameterKind::Flag),
+        ]
+        .into_iter()
+        .find_map(|(table, kind)| {
+            table.iter().find(|p| p.0 == key || p.1.contains(&key)).map(
+                |data| Self {
+                    name: data.0,
+                    aliases: data.1,
+                    description: data.2,
+                    kind,
+                },
+            )
+        })
+    }
+
+    pub(crate) const fn data(
+        &self,
+    ) -> (&'static str, &'static [&'static str], &'static str) {
+        (self.name, self.aliases, self.description)
+    }
 }
 
+pub(super) fn is_flag(key: &str) -> bool {
+    FLAGS.iter().any(|p| p.0 == key || p.1.contains(&key))
+}
+
+/// Whether the key is an alias that should no longer be used.
+pub(super) fn is_deprecated_alias(key: &str) -> bool {
+    DEPRECATED_ALIASES.contains(&key)
+}
+
+/// Checks whether a value is physically unlikely for a parameter, and if so,
+/// returns why.
+pub(super) fn suspicious_value(name: &str, value: f64) -> Option<&'static str> {
+    match name {
+        "ECC" if !(0.0..1.0).contains(&value) => {
+            Some("eccentricity outside [0, 1)")
+        }
+        "SINI" if !(0.0..=1.0).contains(&value) => {
+            Some("sine of inclination outside [0, 1]")
+        }
+        "DM" if value < 0.0 => Some("negative dispersion measure"),
+        "M2" | "MTOT" if value < 0.0 => Some("negative mass"),
+        "PB" | "P0" if value <= 0.0 => Some("non-positive period"),
+        _ => None,
+    }
+}
+
+/// Aliases from the TEMPO days, still accepted.
+const DEPRECATED_ALIASES: &[&str] = &["E", "EDOT", "XDOT"];
+
 /// All documented parfile parameters with f64 values.
 const PARAMETERS: &[(&str, &[&str], &str)] = &[
     ("F0", &[], "The rotational frequency (Hz)"),
@@ -319,8 +460,8 @@ const PARAMETERS: &[(&str, &[&str], &str)] = &[
     ("A1", &[], "Projected semi-major axis of orbit (lt-sec)"),
     ("PB", &[], "Orbital period (days)"),
     (
-        "P1",
-        &["PBDOT"],
+        "PBDOT",
+        &[],
         "1st time derivative of binary period (days / s)",
     ),
     (
@@ -329,12 +470,12 @@ const PARAMETERS: &[(&str, &[&str], &str)] = &[
         "2nd time derivative of binary period (days / s^2)",
     ),
     (
-        "PB2",
+        "PB3",
         &[],
         "3rd time derivative of binary period (days / s^3)",
     ),
     (
-        "PB3",
+        "PB4",
         &[],
         "4th time derivative of binary period (days / s^4)",
     ),
@@ -1,6 +1,8 @@
 #[allow(unused)]
 use super::*;
 #[allow(unused)]
+use crate::warning::WarningKind;
+#[allow(unused)]
 use std::io::{BufReader, LineWriter};
 
 #[test]
@@ -95,6 +97,32 @@ fn duplicate_params() {
     assert!(par.is_err());
 }
 
+#[test]
+fn binary_period_derivatives() {
+    let lines = "
+        PSR J0000-9999\n\
+        RA 23:59:59.999\n\
+        DEC 45:59:59.999\n\
+        PEPOCH 55000\n\
+        F0 9001\n\
+        DM 99\n\
+        P1 1.5\n\
+        PB 10\n\
+        PBDOT 2e-12\n\
+        PB2 3e-20\n\
+        PB3 4e-28\n\
+        PB4 5e-36\n\
+    ";
+
+    let par = Parfile::read(BufReader::new(lines.as_bytes())).unwrap();
+    assert_eq!(par.value("P1"), Some(1.5));
+    assert_eq!(par.value("PDOT"), Some(1.5));
+    assert_eq!(par.value("PBDOT"), Some(2e-12));
+    assert_eq!(par.value("PB2"), Some(3e-20));
+    assert_eq!(par.value("PB3"), Some(4e-28));
+    assert_eq!(par.value("PB4"), Some(5e-36));
+}
+
 #[test]
 fn write_read_invariance() {
     let minimal = "
@@ -124,3 +152,569 @@ fn write_read_invariance() {
 
     assert_eq!(src, dst);
 }
+
+#[test]
+fn multi_word_text() {
+    let text = "\
+PSR J0000-9999
+RA 23:59:59.999
+DEC 45:59:59.999
+PEPOCH 55000
+F0 9001
+DM 1001.1
+EPHEM DE440
+CLK_CORR_CHAIN ao2gps.clk    gps2utc.clk
+";
+
+    let mut par = Parfile::read(BufReader::new(text.as_bytes())).unwrap();
+    assert_eq!(par.text("EPHEM"), Some("DE440"));
+    assert_eq!(par.text("CLK_CORR_CHAIN"), Some("ao2gps.clk gps2utc.clk"));
+
+    let extra = text.replace("DE440", "DE440 DE405");
+    let other = Parfile::read(BufReader::new(extra.as_bytes())).unwrap();
+    assert_eq!(other.text("EPHEM"), Some("DE440"));
+
+    let mut dst = Vec::new();
+    par.write(&mut dst).unwrap();
+    let written = String::from_utf8(dst).unwrap();
+    let line = written
+        .lines()
+        .find(|l| l.starts_with("CLK_CORR_CHAIN"))
+        .unwrap();
+    assert_eq!(
+        line.split_whitespace().collect::<Vec<_>>(),
+        ["CLK_CORR_CHAIN", "ao2gps.clk", "gps2utc.clk"]
+    );
+
+    let again = Parfile::read(BufReader::new(written.as_bytes())).unwrap();
+    assert_eq!(again.text("CLK_CORR_CHAIN"), par.text("CLK_CORR_CHAIN"));
+    assert_eq!(again.text("EPHEM"), Some("DE440"));
+
+    let mut dst = Vec::new();
+    par.write_lossless(&mut dst).unwrap();
+    assert_eq!(String::from_utf8(dst).unwrap(), text);
+
+    assert!(par.set_text("EPHEM", "DE440 DE405").is_err());
+    assert!(par.set_text("EPHEM", " DE405").is_err());
+    assert!(par.set_text("CLK_CORR_CHAIN", "  ").is_err());
+    par.set_text("CLK_CORR_CHAIN", " pks2gps.clk\tgps2utc.clk ")
+        .unwrap();
+    assert_eq!(par.text("CLK_CORR_CHAIN"), Some("pks2gps.clk gps2utc.clk"));
+}
+
+#[test]
+fn lossless_invariance() {
+    let text = "\
+# Some pulsar
+PSRJ           J0000-9999
+RAJ            23:59:59.9990000
+DEC            45:59:59.999
+
+PEPOCH         55000.000
+F0             9001.00000000000000 1 0.000100
+DM             1001.1
+C A tempo-style comment
+GLEP_1         55100.0
+GLF0_1         1e-6
+GLF0D_1        1e-7
+JUMP -fe L-wide 0.1 1
+MODEL BT
+";
+
+    let par = Parfile::read(BufReader::new(text.as_bytes())).unwrap();
+    let mut dst = Vec::new();
+    par.write_lossless(&mut dst).unwrap();
+
+    assert_eq!(text, String::from_utf8(dst).unwrap());
+}
+
+#[test]
+fn lossless_minimal_diff() {
+    let text = "\
+PSRJ   J0000-9999 # The name
+RAJ    23:59:59.999
+DEC    45:59:59.999
+PEPOCH 55000
+F0     9001.000 1 0.0001
+DM     1001.1
+F1     -1e-15
+JUMP MJD 55000 55100 0.1 1  # Receiver change
+";
+
+    let mut par = Parfile::read(BufReader::new(text.as_bytes())).unwrap();
+    par.parameters.retain(|p| p.name() != "F1");
+    par.jumps[0].value = 0.25;
+    par.binary_model = BinaryModel::ELL1;
+    par.counts.push(Parameter::new(&("NITS", &[], ""), 3));
+
+    let mut dst = Vec::new();
+    par.write_lossless(&mut dst).unwrap();
+
+    let expected = "\
+PSRJ   J0000-9999 # The name
+RAJ    23:59:59.999
+DEC    45:59:59.999
+PEPOCH 55000
+F0     9001.000 1 0.0001
+DM     1001.1
+JUMP MJD 55000 55100 0.25 1 # Receiver change
+NITS 3
+MODEL ELL1
+";
+    assert_eq!(expected, String::from_utf8(dst).unwrap());
+}
+
+#[test]
+fn glitch_numbers() {
+    // Glitches are numbered from 1, like in the file, and are stored in
+    // that order whatever order they are read in.
+    let header = "PSRJ J0000-9999\nPEPOCH 55000\nF0 9001\nDM 1001.1\n";
+    let glitch =
+        |n: usize| format!("GLEP_{n} 5510{n}\nGLF0_{n} 1e-6\nGLF0D_{n} 1e-7\n");
+    let text = format!("{header}{}{}", glitch(2), glitch(1));
+    let par = Parfile::read(text.as_bytes()).unwrap();
+
+    let numbers = par.glitches.iter().map(|g| g.number).collect::<Vec<_>>();
+    assert_eq!(numbers, [1, 2]);
+    assert_eq!(par.glitches[0].field("GLEP"), Some(55101.0));
+    assert_eq!(par.glitches[1].field("GLEP"), Some(55102.0));
+
+    let text = format!("{header}GLEP_0 55100\n");
+    assert!(matches!(
+        Parfile::read(text.as_bytes()),
+        Err(PsruError::Unparsable { .. })
+    ));
+}
+
+#[test]
+fn lenient_extras() {
+    let text = "\
+PSRJ     J0000-9999
+RAJ      23:59:59.999
+DECJ     45:59:59.999
+PEPOCH   55000
+F0       9001
+DM       1001.1
+ECORR    -f L-wide_PUPPI 0.0123 1
+TNRedAmp -13.5
+TNSubtractPoly
+";
+
+    let strict = Parfile::read(BufReader::new(text.as_bytes()));
+    assert!(
+        matches!(strict, Err(PsruError::ParUnrecognisedKey(_, k)) if k == "ECORR")
+    );
+
+    let (par, warnings) = Parfile::read_with(
+        BufReader::new(text.as_bytes()),
+        &ParseOptions::lenient(),
+    )
+    .unwrap();
+
+    assert_eq!(par.extras.len(), 3);
+    assert_eq!(par.extras[0].key, "ECORR");
+    assert_eq!(par.extras[0].value, "-f L-wide_PUPPI 0.0123 1");
+    assert_eq!(par.extras[1].line, 8);
+    assert_eq!(par.extras[2].value, "");
+    assert_eq!(
+        warnings.iter().map(|w| w.line).collect::<Vec<_>>(),
+        [7, 8, 9]
+    );
+
+    let mut dst = Vec::new();
+    par.write(&mut dst).unwrap();
+    let dst = String::from_utf8(dst).unwrap();
+    assert!(dst.contains("ECORR -f L-wide_PUPPI 0.0123 1\n"));
+    assert!(dst.contains("TNRedAmp -13.5\n"));
+    assert!(dst.contains("\nTNSubtractPoly\n"));
+
+    let mut dst = Vec::new();
+    par.write_lossless(&mut dst).unwrap();
+    assert_eq!(text, String::from_utf8(dst).unwrap());
+
+    // Known keys still need values, also in lenient mode
+    let text = text.replace("TNSubtractPoly", "F1");
+    let lenient = Parfile::read_with(
+        BufReader::new(text.as_bytes()),
+        &ParseOptions::lenient(),
+    );
+    assert!(
+        matches!(lenient, Err(PsruError::ParMissingValue(_, k)) if k == "F1")
+    );
+}
+
+#[test]
+fn warnings() {
+    let text = "\
+PSRJ     J0000-9999
+RAJ      23:59:59.999
+DECJ     45:59:59.999
+PEPOCH   55000
+F0       9001
+DM       1001.1
+E        1.2
+DM       1001.1
+NOTRACK
+";
+
+    let options = ParseOptions::strict().with_source("test.par");
+    let (par, warnings) =
+        Parfile::read_with(BufReader::new(text.as_bytes()), &options).unwrap();
+
+    let kinds = warnings.iter().map(|w| &w.kind).collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            &WarningKind::DeprecatedAlias {
+                alias: String::from("E"),
+                name: String::from("ECC")
+            },
+            &WarningKind::SuspiciousValue {
+                name: String::from("ECC"),
+                reason: "eccentricity outside [0, 1)"
+            },
+            &WarningKind::DuplicateParameter(String::from("DM")),
+            &WarningKind::EmptyFlag(String::from("NOTRACK")),
+        ]
+    );
+    assert_eq!(warnings[2].line, 8);
+    assert_eq!(warnings[2].file, "test.par");
+
+    assert_eq!(
+        par.parameters.iter().filter(|p| p.name() == "DM").count(),
+        1
+    );
+    assert!(par.flags[0].value());
+}
+
+#[test]
+fn incomplete_glitches() {
+    let text = "\
+PSRJ     J0000-9999
+PEPOCH   55000
+F0       9001
+DM       1001.1
+GLEP_2   55100
+GLF0_2   1e-6
+GLF0D_2  1e-7
+GLEP_3   55200
+";
+
+    let strict = Parfile::read(BufReader::new(text.as_bytes()));
+    assert!(matches!(strict, Err(PsruError::BadGlitch(_, 1))));
+
+    let (par, warnings) = Parfile::read_with(
+        BufReader::new(text.as_bytes()),
+        &ParseOptions::lenient(),
+    )
+    .unwrap();
+    let numbers = par.glitches.iter().map(|g| g.number).collect::<Vec<_>>();
+    assert_eq!(numbers, [2]);
+
+    let found = warnings
+        .iter()
+        .map(|w| (w.line, &w.kind))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found,
+        [
+            (5, &WarningKind::IncompleteGlitch(1)),
+            (8, &WarningKind::IncompleteGlitch(3)),
+        ]
+    );
+
+    let mut dst = Vec::new();
+    par.write_lossless(&mut dst).unwrap();
+    assert!(!String::from_utf8(dst).unwrap().contains("GLEP_3"));
+}
+
+#[test]
+fn error_context() {
+    let text = "\
+PSRJ   J0000-9999
+RAJ    23:59:59.999
+DECJ   45:59:59.999
+PEPOCH 55000
+F0     9OO1 1 0.0001
+DM     1001.1
+";
+    let options = ParseOptions::strict().with_source("test.par");
+    let err = Parfile::read_with(BufReader::new(text.as_bytes()), &options)
+        .unwrap_err();
+
+    let ctx = err.par_ctx().unwrap();
+    assert_eq!(
+        (ctx.source(), ctx.line(), ctx.columns()),
+        ("test.par", 5, (7, 11))
+    );
+    assert_eq!(
+        err.diagnostic(),
+        "\
+error: Impossible to parse '9OO1' into type double.
+ --> test.par:5:8
+  |
+5 | F0     9OO1 1 0.0001
+  |        ^^^^
+"
+    );
+
+    // Unknown keys point at the key
+    let unknown = text
+        .replace("9OO1", "9001")
+        .replace("DM     1001.1", "  TNRedAmp -13.5");
+    let err = Parfile::read_with(BufReader::new(unknown.as_bytes()), &options)
+        .unwrap_err();
+    assert!(matches!(err, PsruError::ParUnrecognisedKey(Some(_), _)));
+    let ctx = err.par_ctx().unwrap();
+    assert_eq!(
+        (ctx.source(), ctx.line(), ctx.columns(), ctx.text()),
+        ("test.par", 6, (2, 10), "  TNRedAmp -13.5")
+    );
+
+    // Errors found after reading point at the last repeat
+    let text = "\
+PSRJ   J0000-9999
+RAJ    23:59:59.999
+DECJ   45:59:59.999
+PEPOCH 55000
+F0     9001
+F1     -1e-15
+DM     1001.1
+F1     -2e-15
+";
+    let err = Parfile::read(BufReader::new(text.as_bytes())).unwrap_err();
+    assert!(matches!(err, PsruError::ParDuplicateParameters(..)));
+    assert_eq!(err.par_ctx().unwrap().line(), 8);
+
+    // Missing parameters concern the whole file
+    let head = &text[..text.find("F0").unwrap()];
+    let err = Parfile::read(BufReader::new(head.as_bytes())).unwrap_err();
+    assert!(matches!(err, PsruError::ParNoFrequency(Some(_))));
+    assert_eq!(err.par_ctx().unwrap().line(), 0);
+}
+
+#[test]
+fn edit_parameters() {
+    let text = "\
+PSRJ   J0000-9999
+RAJ    23:59:59.999
+DECJ   45:59:59.999
+PEPOCH 55000
+F0     9001 1 0.0001
+F1     -1e-15
+DM     1001.1
+XDOT   1e-12
+";
+    let mut par = Parfile::read(BufReader::new(text.as_bytes())).unwrap();
+
+    assert_eq!(par.text("PSRJ"), Some("J0000-9999"));
+    assert_eq!(par.value("A1DOT"), Some(1e-12));
+    assert_eq!(par.flag("NOTRACK"), None);
+
+    par.set_value("F2", 1e-25).unwrap();
+    par.set_value("F0", 9002.0).unwrap();
+    par.set_fit("DM", true).unwrap();
+    par.set_uncertainty("DM", 0.5).unwrap();
+    par.set_count("NITS", 3).unwrap();
+    par.set_flag("NOTRACK", true).unwrap();
+    assert!(par.remove("PDOT").is_ok_and(|removed| !removed));
+    assert!(par.remove("F1").unwrap());
+
+    assert!(par.set_value("F0", 0.0).is_err());
+    assert!(par.set_value("F0", f64::NAN).is_err());
+    assert!(par.set_value("PEPOCH", f64::NAN).is_err());
+    assert!(par.set_value("F2", f64::INFINITY).is_err());
+    assert!(par.set_uncertainty("DM", f64::INFINITY).is_err());
+    assert!(par.set_value("F7", 0.0).is_err());
+    assert!(par.set_count("F2", 1).is_err());
+    assert!(par.set_fit("PX", true).is_err());
+    assert!(par.set_text("EPHEM", "DE 440").is_err());
+    assert!(par.remove("PEPOCH").is_err());
+
+    assert_eq!(
+        par.parameter("F0").unwrap().value(),
+        &FittedParameterValue::FitInfo {
+            value: 9002.0,
+            fit: true,
+            error: 0.0001
+        }
+    );
+
+    let mut dst = Vec::new();
+    par.write_lossless(&mut dst).unwrap();
+    let expected = "\
+PSRJ   J0000-9999
+RAJ    23:59:59.999
+DECJ   45:59:59.999
+PEPOCH 55000
+F0     9002 1 0.0001
+DM     1001.1 1 0.5
+XDOT   1e-12
+F2 1e-25
+NITS 3
+NOTRACK Y
+";
+    assert_eq!(expected, String::from_utf8(dst).unwrap());
+}
+
+#[test]
+fn number_format() {
+    use crate::parse_tools::format_f64;
+
+    for (value, text) in [
+        (1e-25, "1e-25"),
+        (0.0001, "0.0001"),
+        (0.001, "0.001"),
+        (0.01, "0.01"),
+        (1e-6, "0.000001"),
+        (1e-7, "1e-7"),
+        (-2.5e-7, "-2.5e-7"),
+        (1000.0, "1000"),
+        (9001.0, "9001"),
+        (50000.0, "50000"),
+        (55_000.123_456, "55000.123456"),
+        (1001.1, "1001.1"),
+        (1e21, "1e21"),
+        (123_456_789.0, "123456789"),
+        (0.0, "0"),
+    ] {
+        assert_eq!(format_f64(value), text);
+        assert_eq!(text.parse::<f64>(), Ok(value));
+    }
+}
+
+#[test]
+fn plain_numbers() {
+    let text = "\
+PSRJ   J0000-9999
+RAJ    23:59:59.999
+DECJ   45:59:59.999
+PEPOCH 55000
+F0     9001
+DM     1001.1
+";
+    let mut par = Parfile::read(BufReader::new(text.as_bytes())).unwrap();
+    par.set_value("PEPOCH", 50000.0).unwrap();
+    par.set_value("F0", 1000.0).unwrap();
+    par.set_uncertainty("F0", 0.001).unwrap();
+    par.set_value("F1", -1.5e-15).unwrap();
+
+    let mut dst = Vec::new();
+    par.write_lossless(&mut dst).unwrap();
+    let expected = "\
+PSRJ   J0000-9999
+RAJ    23:59:59.999
+DECJ   45:59:59.999
+PEPOCH 50000
+F0     1000 0 0.001
+DM     1001.1
+F1 -1.5e-15
+";
+    assert_eq!(expected, String::from_utf8(dst).unwrap());
+}
+
+#[test]
+fn jump_selection() {
+    let text = "\
+PSR J0000-9999
+RA 23:59:59.999
+DEC 45:59:59.999
+PEPOCH 55000
+F0 9001
+DM 1001.1
+JUMP -fe L-wide 0.1 1
+JUMP MJD 55000 55100 0.1 1
+JUMP TEL gbt 0.1 1
+";
+    let par = Parfile::read(BufReader::new(text.as_bytes())).unwrap();
+    let toas = [
+        "a 1400 55050.5 1.0 ao -fe L-wide",
+        "a 430 56000.5 1.0 gbt -fe 430",
+    ]
+    .map(|l| crate::timfile::TOAInfo::from_line_tempo2(l).unwrap());
+
+    let matched = par
+        .jumps
+        .iter()
+        .map(|j| toas.iter().map(|t| j.jtype.matches(t)).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    assert_eq!(matched, [[true, false], [true, false], [false, true]]);
+
+    let selection = par.jump_selection(&toas);
+    assert_eq!(selection.toas(1), [0]);
+    assert_eq!(selection.jumps(0), [0, 1]);
+    assert!(selection.empty().is_empty());
+    assert_eq!(selection.overlaps(), [(0, 1)]);
+    assert!(selection.reference().is_empty());
+    assert_eq!(selection.offsets(), [0.2, 0.1]);
+
+    let selection = par.jump_selection(&[]);
+    assert_eq!(selection.empty(), [0, 1, 2]);
+    assert!(selection.overlaps().is_empty());
+}
+
+#[test]
+fn jump_round_trip() {
+    let header = "\
+PSR J0000-9999
+RA 23:59:59.999
+DEC 45:59:59.999
+PEPOCH 55000
+F0 9001
+DM 1001.1
+";
+    // As written by Tempo2, PINT, and by hand
+    let variants = [
+        ("JUMP MJD 55000 55100 0.1 1", "JUMP MJD 55000 55100 0.1 1"),
+        ("JUMP FREQ 1000 2000 -0.5 0", "JUMP FREQ 1000 2000 -0.5 0"),
+        ("JUMP TEL ao 1e-5 1 2e-7", "JUMP TEL ao 0.00001 1 2e-7"),
+        ("JUMP NAME file.ar 0.001", "JUMP NAME file.ar 0.001 0"),
+        ("JUMP -fe L-wide 0.0 1 1e-6", "JUMP -fe L-wide 0 1 0.000001"),
+        ("JUMP -sys X", "JUMP -sys X 0 0"),
+        ("JUMP -be GUPPI 1.234e-05 0", "JUMP -be GUPPI 1.234e-5 0"),
+        ("JUMP mjd 55000 55100", "JUMP MJD 55000 55100 0 0"),
+        ("JUMP tel gbt 0.1 y", "JUMP TEL gbt 0.1 1"),
+    ];
+
+    for (line, written) in variants {
+        let text = format!("{header}{line}\n");
+        let par = Parfile::read(BufReader::new(text.as_bytes())).unwrap();
+        assert_eq!(par.jumps.len(), 1, "{line}");
+        assert_eq!(par.jumps[0].write(), format!("{written}\n"));
+
+        let mut dst = Vec::new();
+        par.write(&mut dst).unwrap();
+        let again = Parfile::read(BufReader::new(dst.as_slice())).unwrap();
+        assert_eq!(again.jumps, par.jumps, "{line}");
+
+        let mut dst = Vec::new();
+        par.write_lossless(&mut dst).unwrap();
+        assert_eq!(String::from_utf8(dst).unwrap(), text);
+    }
+
+    let text = format!("{header}JUMP -fe L-wide 0.0 1 1e-6\n");
+    let par = Parfile::read(BufReader::new(text.as_bytes())).unwrap();
+    assert_eq!(
+        par.jumps[0],
+        Jump {
+            jtype: JumpType::Flag(String::from("-fe"), String::from("L-wide")),
+            value: 0.0,
+            fit: true,
+            error: Some(1e-6),
+        }
+    );
+
+    for line in [
+        "JUMP MJD 55000",
+        "JUMP TEL",
+        "JUMP -fe",
+        "JUMP -fe X 0.1 1 0.01 extra",
+        "JUMP -fe X 0.1 2",
+    ] {
+        let text = format!("{header}{line}\n");
+        assert!(
+            Parfile::read(BufReader::new(text.as_bytes())).is_err(),
+            "'{line}' should fail"
+        );
+    }
+}
@@ -2,26 +2,42 @@ use crate::error::PsruError;
 type Result<T> = std::result::Result<T, PsruError>;
 
 pub fn parse_f64(value: &str) -> Result<f64> {
-    value.parse().map_err(|_| PsruError::Unparsable {
-        value: value.to_string(),
-        to_type: "double",
-    })
+    value
+        .parse()
+        .map_err(|_| PsruError::unparsable(value, "double"))
+}
+
+/// Plain numbers up to this long are always written as they are.
+const PLAIN_WIDTH: usize = 8;
+
+/// Writes a double so that it reads back the same. The plain form is used,
+/// e.g. `50000` or `0.001`, unless it is longer than [`PLAIN_WIDTH`] and the
+/// exponent form is shorter, e.g. `1e-25` rather than
+/// `0.0000000000000000000000001`.
+pub fn format_f64(value: f64) -> String {
+    let plain = value.to_string();
+    if plain.len() <= PLAIN_WIDTH {
+        return plain;
+    }
+
+    let exponent = format!("{value:e}");
+    if exponent.len() < plain.len() {
+        exponent
+    } else {
+        plain
+    }
 }
 
 pub fn parse_u32(value: &str) -> Result<u32> {
-    value.parse().map_err(|_| PsruError::Unparsable {
-        value: value.to_string(),
-        to_type: "integer",
-    })
+    value
+        .parse()
+        .map_err(|_| PsruError::unparsable(value, "integer"))
 }
 
 pub fn parse_bool(value: &str) -> Result<bool> {
     match value {
         "1" | "Y" | "y" => Ok(true),
         "0" | "N" | "n" => Ok(false),
-        _ => Err(PsruError::Unparsable {
-            value: value.to_string(),
-            to_type: "bool",
-        }),
+        _ => Err(PsruError::unparsable(value, "bool")),
     }
 }
@@ -0,0 +1,34 @@
+//! Scratch directories for tests that need files.
+
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// An empty directory of its own for a test, which is removed when dropped,
+/// also if the test fails.
+pub struct TestDir(PathBuf);
+impl TestDir {
+    /// Makes the directory, named after the test, and unique to the process
+    /// and the call.
+    pub fn new(name: &str) -> Self {
+        static MADE: AtomicUsize = AtomicUsize::new(0);
+        let count = MADE.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir()
+            .join(format!("psrutils-{name}-{}-{count}", std::process::id()));
+
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+impl Deref for TestDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        _ = std::fs::remove_dir_all(&self.0);
+    }
+}
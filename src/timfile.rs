@@ -1,21 +1,52 @@
 //! Allows for reading the data of `.tim` files.
 
-use crate::error::{PsruError, TimContext};
+use crate::{error::PsruError, warning::Warning};
 use std::{
     fs::File,
     io::{BufRead, BufReader},
-    path::{Path, PathBuf},
+    path::Path,
 };
 
+#[cfg(not(feature = "rayon"))]
+use iter::read_all;
+#[cfg(feature = "rayon")]
+use parallel::read_all;
+
+pub use include::{FsResolver, IncludeResolver, MemoryResolver, TimSource};
+pub use iter::Toas;
+pub use select::{Condition, Selector};
+pub use summary::Summary;
+pub use table::{FlagRef, ToaTable};
 pub use toa::*;
+pub use write::write_tim;
 
+mod directive;
+mod include;
+mod iter;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod select;
+mod summary;
+mod table;
 mod tests;
 mod toa;
+mod write;
 
 /// Reads a .tim file recursively. Returns errors for missing TOA values,
 /// flags without values, and malformed entries.
 ///
-/// Currently, the only implemented format is for Tempo2.
+/// The directives `TIME`, `JUMP`, `EFAC`, `EQUAD`, `SKIP`/`NOSKIP`,
+/// `PHASE`, `EMIN`/`EMAX`, and `FMIN`/`FMAX` are applied to the TOAs that
+/// follow them, in the same file or those it includes: MJDs are offset by
+/// `TIME` seconds, uncertainties are multiplied by `EFAC` before `EQUAD` is
+/// added in quadrature, `PHASE` adds to the `phase` flag, TOAs within `JUMP`
+/// pairs get a `tim_jump` flag numbering the region, and skipped or cut
+/// TOAs are left out. The directive lines are kept in
+/// [`TOAInfo::directives`].
+///
+/// All formats accepted by TEMPO and Tempo2 are implemented, see
+/// [`TimFormat`]. With [`TimFormat::Auto`], the format is decided separately
+/// for each file, including those that are `INCLUDE`d.
 ///
 /// # Errors
 /// Will throw errors for bad files or contents.
@@ -23,75 +54,245 @@ pub fn read_tim(
     path: &Path,
     format: TimFormat,
 ) -> Result<Vec<TOAInfo>, PsruError> {
-    let mut toa_infos = Vec::new();
-
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
+    read_tim_with_warnings(path, format).map(|(toa_infos, _)| toa_infos)
+}
 
-    let directory = path.parent().ok_or(PsruError::OrphanFile)?.to_path_buf();
-    let mut ctx = TimContext::new(&path.to_string_lossy(), 0);
+/// Reads a .tim file recursively, like [`read_tim`], but also returns
+/// warnings about recoverable oddities.
+///
+/// These are directives that are known but not acted upon (which are
+/// skipped), flags without values (which are given empty values), and
+/// suspicious values, such as non-positive uncertainties.
+///
+/// # Errors
+/// Will throw errors for bad files or contents.
+pub fn read_tim_with_warnings(
+    path: &Path,
+    format: TimFormat,
+) -> Result<(Vec<TOAInfo>, Vec<Warning>), PsruError> {
+    TimReader::new(format).read_path(path)
+}
 
-    for (line_number, result) in reader.lines().enumerate() {
-        let line = result?;
-        if line.is_empty() {
-            continue;
-        }
+/// Lazily reads a .tim file recursively, like [`read_tim`], yielding one TOA
+/// at a time. See [`Toas`].
+///
+/// # Errors
+/// Fails if the file cannot be opened. Errors in the contents come up while
+/// iterating.
+pub fn iter_tim(
+    path: &Path,
+    format: TimFormat,
+) -> Result<Toas<'static>, PsruError> {
+    TimReader::new(format).toas_path(path)
+}
 
-        ctx.line(line_number + 1);
+/// Reads a .tim file recursively into a [`ToaTable`], like [`read_tim`],
+/// without keeping every `TOAInfo` around on the way.
+///
+/// # Errors
+/// Will throw errors for bad files or contents.
+pub fn read_tim_table(
+    path: &Path,
+    format: TimFormat,
+) -> Result<ToaTable, PsruError> {
+    iter_tim(path, format)?.collect()
+}
 
-        parse_line(format, directory.clone(), &mut toa_infos, &line)
-            .map_err(|err| err.set_tim_ctx(&ctx))?;
+/// Reads `.tim` data from any source, finding the data of `INCLUDE`d files
+/// through an [`IncludeResolver`]. By default, includes are read from the
+/// filesystem.
+///
+/// ```
+/// # use psrutils::timfile::{MemoryResolver, TimFormat, TimReader};
+/// let resolver = MemoryResolver::new()
+///     .with_file("b.tim", "fname 1400.0 55001.5 1.0 ao\n");
+/// let main = "FORMAT 1\nfname 1400.0 55000.5 1.0 ao\nINCLUDE b.tim\n";
+///
+/// let (toas, warnings) = TimReader::new(TimFormat::Auto)
+///     .with_resolver(&resolver)
+///     .read(main.as_bytes(), "main.tim")
+///     .unwrap();
+///
+/// assert_eq!(toas.len(), 2);
+/// assert!(warnings.is_empty());
+/// ```
+#[derive(Clone, Copy)]
+pub struct TimReader<'r> {
+    format: TimFormat,
+    resolver: &'r dyn IncludeResolver,
+    max_depth: usize,
+    dedup: bool,
+}
+impl TimReader<'static> {
+    /// A reader for the given format, which reads includes from the
+    /// filesystem, at most [`TimReader::DEFAULT_MAX_DEPTH`] levels deep.
+    pub const fn new(format: TimFormat) -> Self {
+        Self {
+            format,
+            resolver: &FsResolver,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            dedup: false,
+        }
     }
-
-    Ok(toa_infos)
 }
+impl TimReader<'_> {
+    /// How many levels of `INCLUDE`s are followed by default.
+    pub const DEFAULT_MAX_DEPTH: usize = 32;
 
-fn parse_line(
-    mode: TimFormat,
-    mut directory: PathBuf,
-    toa_infos: &mut Vec<TOAInfo>,
-    line: &str,
-) -> Result<(), PsruError> {
-    let parts = line.split_whitespace().collect::<Vec<_>>();
-
-    if parts[0] == "INCLUDE" {
-        directory.push(parts[1]);
-        let mut nested_tim = read_tim(&directory, mode)?;
+    /// Sets how many levels of `INCLUDE`s are followed before giving an
+    /// error. With a depth of 0, no includes are allowed at all.
+    #[must_use]
+    pub const fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
 
-        toa_infos.append(&mut nested_tim);
-        return Ok(());
+    /// Sets whether a file that is included more than once, e.g. from two
+    /// different parents, is read only the first time. Repeats are skipped
+    /// with a warning.
+    #[must_use]
+    pub const fn with_dedup(mut self, dedup: bool) -> Self {
+        self.dedup = dedup;
+        self
     }
 
-    if parts[0] == "FORMAT" && parts[1] == "1" {
-        if mode != TimFormat::Tempo2 {
-            return Err(PsruError::TimFormatDiscrepancy(
-                None,
-                String::from("Tempo2"),
-            ));
+    /// Uses another way of finding the data of `INCLUDE`d files.
+    #[must_use]
+    pub const fn with_resolver(
+        self,
+        resolver: &dyn IncludeResolver,
+    ) -> TimReader<'_> {
+        TimReader {
+            format: self.format,
+            resolver,
+            max_depth: self.max_depth,
+            dedup: self.dedup,
         }
-        return Ok(());
     }
 
-    if parts[0] == "MODE" && parts[1] == "1" {
-        // I don't know what this means
-        return Ok(());
+    /// Reads `.tim` data, along with any files it includes. The name is used
+    /// in errors and warnings, and is given to the resolver to find includes
+    /// relative to.
+    ///
+    /// Files are told apart by the names the resolver gives them, which are
+    /// used to detect files that include themselves, directly or through
+    /// others.
+    ///
+    /// With the `rayon` feature, each file is parsed in parallel with the
+    /// others once it has been read, and the lines of large files in
+    /// parallel chunks. Reading stops once a file turns out to have an
+    /// error. The results are the same as without it.
+    ///
+    /// # Errors
+    /// Will throw errors for bad contents, includes that cannot be found,
+    /// include cycles, and includes nested too deeply.
+    pub fn read(
+        &self,
+        reader: impl BufRead,
+        name: &str,
+    ) -> Result<(Vec<TOAInfo>, Vec<Warning>), PsruError> {
+        read_all(self, reader, name)
     }
 
-    let toa_info = match mode {
-        TimFormat::Tempo2 => TOAInfo::parse_tempo2(&parts)?,
-        TimFormat::Parkes => TOAInfo::parse_parkes(line)?,
-    };
-
-    toa_infos.push(toa_info);
+    /// Reads a `.tim` file from the filesystem, like [`TimReader::read`].
+    /// The file is named by its canonical path, like [`FsResolver`] names
+    /// the files it includes.
+    ///
+    /// # Errors
+    /// Will throw errors for bad files or contents.
+    pub fn read_path(
+        &self,
+        path: &Path,
+    ) -> Result<(Vec<TOAInfo>, Vec<Warning>), PsruError> {
+        let path = std::fs::canonicalize(path)?;
+        let file = BufReader::new(File::open(&path)?);
+        self.read(file, &path.to_string_lossy())
+    }
+}
+impl<'r> TimReader<'r> {
+    /// Lazily reads the TOAs of `.tim` data, along with any files it
+    /// includes, as [`TimReader::read`] does. Only the files currently being
+    /// read are kept open, and nothing is read ahead of the TOA asked for.
+    pub fn toas(&self, reader: impl BufRead + 'r, name: &str) -> Toas<'r> {
+        Toas::new(*self, Box::new(reader), name)
+    }
 
-    Ok(())
+    /// Lazily reads the TOAs of a `.tim` file from the filesystem, like
+    /// [`TimReader::toas`]. The file is named by its canonical path.
+    ///
+    /// # Errors
+    /// Fails if the file cannot be opened.
+    pub fn toas_path(&self, path: &Path) -> Result<Toas<'r>, PsruError> {
+        let path = std::fs::canonicalize(path)?;
+        let file = BufReader::new(File::open(&path)?);
+        Ok(self.toas(file, &path.to_string_lossy()))
+    }
 }
 
+/// In-file commands from TEMPO and Tempo2 that are recognised, but not (yet)
+/// acted upon.
+const UNSUPPORTED_DIRECTIVES: &[&str] =
+    &["MODE", "GLOBAL_EFAC", "SIGMA", "INFO", "TRACK", "END"];
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 /// The format used for parsing TOAs in .tim files.
 pub enum TimFormat {
     /// Read `.tim` files the way Tempo2 likes it.
     Tempo2,
-    /// Not implemented.
+    /// Read `.tim` files in the fixed-width Parkes format, as used by TEMPO.
+    /// This is also what is found in older files without a `FORMAT` line.
     Parkes,
+    /// Read `.tim` files in the fixed-width Princeton format, as used by
+    /// TEMPO.
+    Princeton,
+    /// Read `.tim` files in the fixed-width ITOA format, as used by TEMPO.
+    Itoa,
+    /// Decide on the format for each file, from its `FORMAT` directive or
+    /// the shape of its first TOA line. This is never the recorded format
+    /// of a TOA.
+    Auto,
+}
+impl TimFormat {
+    /// Guesses the format of a TOA line from its shape, i.e. the presence of
+    /// flags and the positions of its columns. Lines that fit none of the
+    /// fixed-width formats are taken to be Tempo2.
+    ///
+    /// ```
+    /// # use psrutils::timfile::TimFormat;
+    /// let tempo2 = "fname 1400.0 55000.5 1.5 pks -fe H-OH";
+    /// let parkes = " J0437-4715               1400.000  55000.5000000000000    0.10    1.50        7";
+    ///
+    /// assert_eq!(TimFormat::detect(tempo2), TimFormat::Tempo2);
+    /// assert_eq!(TimFormat::detect(parkes), TimFormat::Parkes);
+    /// ```
+    pub fn detect(line: &str) -> Self {
+        let bytes = line.as_bytes();
+        let at = |i: usize| bytes.get(i).copied().unwrap_or(b' ');
+        let has_flags = line.split_ascii_whitespace().any(|word| {
+            word.strip_prefix('-')
+                .is_some_and(|f| f.starts_with(|c: char| c.is_alphabetic()))
+        });
+
+        if has_flags || !line.is_ascii() {
+            Self::Tempo2
+        } else if bytes.len() >= 80
+            && matches!(at(0), b' ' | b'C' | b'c')
+            && at(41) == b'.'
+        {
+            Self::Parkes
+        } else if bytes.len() >= 59
+            && at(14) == b'.'
+            && at(57).is_ascii_alphanumeric()
+        {
+            Self::Itoa
+        } else if bytes.len() >= 53
+            && at(0) != b' '
+            && (at(1) == b' ' || matches!(at(0), b'C' | b'c'))
+            && (at(29) == b'.' || at(30) == b'.')
+        {
+            Self::Princeton
+        } else {
+            Self::Tempo2
+        }
+    }
 }
@@ -0,0 +1,150 @@
+//! The in-file commands of TEMPO and Tempo2, which modify the TOAs after
+//! them.
+
+use super::{Flag, TOAInfo};
+use crate::data_types::TimeDelta;
+use crate::error::PsruError;
+
+/// The directives that act on the TOAs after them.
+const COMMANDS: &[&str] = &[
+    "TIME", "JUMP", "EFAC", "EQUAD", "SKIP", "NOSKIP", "PHASE", "EMIN", "EMAX",
+    "FMIN", "FMAX",
+];
+
+/// Whether a word starts one of the directives [`TimState::command`] acts
+/// on.
+pub(super) fn is_command(word: &str) -> bool {
+    COMMANDS.contains(&word)
+}
+
+/// What the directives read so far do to the TOAs that follow. Each file
+/// starts with a copy of the state of the file that included it, and changes
+/// made within do not carry back out.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct TimState {
+    /// Seconds added to the MJDs, from `TIME`.
+    time: f64,
+    /// The number of the open `JUMP` region, if any.
+    jump: Option<usize>,
+    /// Multiplier for the uncertainties, from `EFAC`.
+    efac: f64,
+    /// Added in quadrature to the uncertainties (µs), from `EQUAD`.
+    equad: f64,
+    /// Whether TOAs are being left out, from `SKIP` and `NOSKIP`.
+    skip: bool,
+    /// Pulses added to the phases, from `PHASE`.
+    phase: f64,
+    /// Bounds on the uncertainties (µs), from `EMIN` and `EMAX`.
+    errors: (f64, f64),
+    /// Bounds on the frequencies (MHz), from `FMIN` and `FMAX`.
+    frequencies: (f64, f64),
+}
+impl Default for TimState {
+    fn default() -> Self {
+        Self {
+            time: 0.0,
+            jump: None,
+            efac: 1.0,
+            equad: 0.0,
+            skip: false,
+            phase: 0.0,
+            errors: (f64::NEG_INFINITY, f64::INFINITY),
+            frequencies: (f64::NEG_INFINITY, f64::INFINITY),
+        }
+    }
+}
+impl TimState {
+    /// Acts on a directive, see [`is_command`]. `jumps` counts the `JUMP`
+    /// regions opened so far, in any file.
+    pub(super) fn command(
+        &mut self,
+        parts: &[&str],
+        jumps: &mut usize,
+    ) -> Result<(), PsruError> {
+        match parts[0] {
+            "TIME" => self.time = added_value(self.time, parts)?,
+            "JUMP" => {
+                self.jump = if self.jump.is_some() {
+                    None
+                } else {
+                    *jumps += 1;
+                    Some(*jumps)
+                };
+            }
+            "EFAC" => self.efac = directive_value(parts)?,
+            "EQUAD" => self.equad = directive_value(parts)?,
+            "SKIP" => self.skip = true,
+            "NOSKIP" => self.skip = false,
+            "PHASE" => self.phase = added_value(self.phase, parts)?,
+            "EMIN" => self.errors.0 = directive_value(parts)?,
+            "EMAX" => self.errors.1 = directive_value(parts)?,
+            "FMIN" => self.frequencies.0 = directive_value(parts)?,
+            "FMAX" => self.frequencies.1 = directive_value(parts)?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Modifies a TOA as the directives say, or gives `None` if it is to be
+    /// left out. The cuts apply to the uncertainties as written in the file.
+    ///
+    /// Fails if `TIME` moves the TOA out of the range of an MJD.
+    #[allow(clippy::float_cmp)]
+    pub(super) fn apply(
+        &self,
+        mut toa: TOAInfo,
+    ) -> Result<Option<TOAInfo>, PsruError> {
+        let (emin, emax) = self.errors;
+        let (fmin, fmax) = self.frequencies;
+        if self.skip
+            || !(emin..=emax).contains(&toa.mjd_error)
+            || !(fmin..=fmax).contains(&toa.frequency)
+        {
+            return Ok(None);
+        }
+
+        if self.time != 0.0 {
+            toa.mjd = TimeDelta::checked_from_seconds(self.time)
+                .and_then(|delta| toa.mjd.checked_add(delta))
+                .ok_or_else(|| {
+                    PsruError::TimDirectiveOutOfRange(
+                        None,
+                        format!("TIME {}", self.time),
+                    )
+                })?;
+        }
+        if self.efac != 1.0 || self.equad != 0.0 {
+            toa.mjd_error = (toa.mjd_error * self.efac).hypot(self.equad);
+        }
+        if self.phase != 0.0 {
+            let phase = toa.flag_f64("phase").unwrap_or_default() + self.phase;
+            toa.flags
+                .insert(String::from("phase"), Flag::new(phase.to_string()));
+        }
+        if let Some(jump) = self.jump {
+            let jump = Flag::new(jump.to_string());
+            toa.flags.insert(String::from("tim_jump"), jump);
+        }
+
+        Ok(Some(toa))
+    }
+}
+
+/// The single, finite number following a directive.
+fn directive_value(parts: &[&str]) -> Result<f64, PsruError> {
+    match parts {
+        [_, value, ..] => value.parse::<f64>().ok(),
+        _ => None,
+    }
+    .filter(|value| value.is_finite())
+    .ok_or_else(|| PsruError::TimBadDirective(None, parts.join(" ")))
+}
+
+/// The number following a directive added to `total`, which must stay
+/// finite.
+fn added_value(total: f64, parts: &[&str]) -> Result<f64, PsruError> {
+    Some(total + directive_value(parts)?)
+        .filter(|total| total.is_finite())
+        .ok_or_else(|| PsruError::TimBadDirective(None, parts.join(" ")))
+}
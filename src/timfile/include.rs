@@ -0,0 +1,177 @@
+//! Finding the data of `INCLUDE`d `.tim` files.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Component, Path, PathBuf},
+};
+
+use crate::error::PsruError;
+
+/// A source of `.tim` data: its name, and a reader for its contents.
+pub type TimSource<'a> = (String, Box<dyn BufRead + 'a>);
+
+/// Maps the target of an `INCLUDE` directive to the data it refers to.
+///
+/// Implemented for the filesystem ([`FsResolver`]), for files held in
+/// memory ([`MemoryResolver`]), and for closures, which makes it simple to
+/// fetch files from elsewhere, e.g. from a database or an archive:
+///
+/// ```
+/// # use psrutils::timfile::{TimFormat, TimReader, TimSource};
+/// # use psrutils::error::PsruError;
+/// let resolver =
+///     |_parent: &str, target: &str| -> Result<TimSource, PsruError> {
+///         // Look up the target in some archive
+///         let contents = "fname 1400.0 55000.5 1.0 ao\n";
+///         Ok((target.to_string(), Box::new(contents.as_bytes())))
+///     };
+///
+/// let reader = TimReader::new(TimFormat::Tempo2).with_resolver(&resolver);
+/// let main = "INCLUDE a.tim\nINCLUDE b.tim\n";
+/// let (toas, _) = reader.read(main.as_bytes(), "main.tim").unwrap();
+///
+/// assert_eq!(toas.len(), 2);
+/// ```
+pub trait IncludeResolver {
+    /// Finds the data for `target`, as written in an `INCLUDE` directive of
+    /// the source named `parent`. The name given back is used for the
+    /// included data from then on, e.g. in errors and for its own includes.
+    ///
+    /// # Errors
+    /// Should give an error if the target cannot be found or opened.
+    fn resolve(
+        &self,
+        parent: &str,
+        target: &str,
+    ) -> Result<TimSource<'_>, PsruError>;
+}
+
+impl<F> IncludeResolver for F
+where
+    F: Fn(&str, &str) -> Result<TimSource<'static>, PsruError>,
+{
+    fn resolve(
+        &self,
+        parent: &str,
+        target: &str,
+    ) -> Result<TimSource<'_>, PsruError> {
+        self(parent, target)
+    }
+}
+
+/// Resolves includes as files relative to the directory of the including
+/// file, which is how TEMPO and Tempo2 do it.
+///
+/// Files are named by their canonical paths, so that different ways of
+/// writing the same path, or links to the same file, are recognised as one
+/// file.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsResolver;
+impl IncludeResolver for FsResolver {
+    fn resolve(
+        &self,
+        parent: &str,
+        target: &str,
+    ) -> Result<TimSource<'_>, PsruError> {
+        let directory =
+            Path::new(parent).parent().ok_or(PsruError::OrphanFile)?;
+        let path = std::fs::canonicalize(directory.join(target))?;
+        let file = File::open(&path)?;
+
+        Ok((
+            path.to_string_lossy().into_owned(),
+            Box::new(BufReader::new(file)),
+        ))
+    }
+}
+
+/// Resolves includes from a set of named files held in memory. Targets are
+/// looked up relative to the directory of the including file first, and
+/// then as they are written.
+///
+/// Names are normalised, both for the files added and for the targets: `.`
+/// is dropped, and `..` removes the directory before it.
+///
+/// ```
+/// # use psrutils::timfile::{MemoryResolver, TimFormat, TimReader};
+/// let resolver = MemoryResolver::new()
+///     .with_file("data/a.tim", "fname 1400.0 55000.5 1.0 ao\n");
+///
+/// let reader = TimReader::new(TimFormat::Tempo2).with_resolver(&resolver);
+/// let main = "INCLUDE a.tim\n";
+/// let (toas, _) = reader.read(main.as_bytes(), "data/main.tim").unwrap();
+///
+/// assert_eq!(toas[0].mjd.int(), 55000);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MemoryResolver {
+    files: HashMap<String, String>,
+}
+impl MemoryResolver {
+    /// An empty set of files.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file with the given name and contents.
+    #[must_use]
+    pub fn with_file(mut self, name: &str, contents: &str) -> Self {
+        self.insert(name, contents);
+        self
+    }
+
+    /// Adds a file with the given name and contents, replacing any file
+    /// with the same name.
+    pub fn insert(&mut self, name: &str, contents: &str) {
+        self.files.insert(normalise(name), contents.to_string());
+    }
+}
+impl IncludeResolver for MemoryResolver {
+    fn resolve(
+        &self,
+        parent: &str,
+        target: &str,
+    ) -> Result<TimSource<'_>, PsruError> {
+        let relative = Path::new(parent)
+            .parent()
+            .map(|directory| normalise(directory.join(target)));
+
+        relative
+            .into_iter()
+            .chain(std::iter::once(normalise(target)))
+            .find_map(|name| {
+                let contents = self.files.get(&name)?;
+                Some((name, Box::new(contents.as_bytes()) as Box<dyn BufRead>))
+            })
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("No file '{target}' included from '{parent}'."),
+                )
+                .into()
+            })
+    }
+}
+
+/// Drops `.` from a path, and resolves `..` against the directory before
+/// it, without looking at any filesystem.
+fn normalise(path: impl AsRef<Path>) -> String {
+    let mut normal = PathBuf::new();
+    for component in path.as_ref().components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normal.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normal.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normal.push(component),
+            },
+            _ => normal.push(component),
+        }
+    }
+
+    normal.to_string_lossy().into_owned()
+}
@@ -0,0 +1,375 @@
+//! Lazily reading TOAs, one line at a time.
+
+use std::{
+    collections::HashSet,
+    io::{BufRead, Lines},
+};
+
+use super::directive::{TimState, is_command};
+use super::{TOAInfo, TimFormat, TimReader, UNSUPPORTED_DIRECTIVES};
+use crate::{
+    error::{PsruError, TimContext},
+    warning::{Warning, WarningKind},
+};
+
+/// An iterator over the TOAs of `.tim` data and the files it includes, which
+/// reads only as far as needed for the next TOA. Made by [`TimReader::toas`].
+///
+/// After the first error, nothing more is read. Warnings are gathered as
+/// reading goes on, and can be taken out at any point.
+///
+/// ```
+/// # use psrutils::timfile::{TimFormat, TimReader};
+/// let data = "\
+/// a 1400.0 55000.5 1.0 ao
+/// a 1400.0 55001.5 0.0 ao
+/// a 1400.0 55002.5 1.0
+/// a 1400.0 55003.5 1.0 ao
+/// ";
+///
+/// let mut toas = TimReader::new(TimFormat::Tempo2).toas(data.as_bytes(), "a.tim");
+///
+/// assert!(toas.next().unwrap().is_ok());
+/// assert!(toas.next().unwrap().is_ok());
+/// assert_eq!(toas.take_warnings().len(), 1);
+/// assert!(toas.next().unwrap().is_err());
+/// assert!(toas.next().is_none());
+/// ```
+pub struct Toas<'r> {
+    reader: TimReader<'r>,
+    /// The files being read, starting with the outermost.
+    stack: Vec<Frame<'r>>,
+    /// The names of all files read so far.
+    seen: HashSet<String>,
+    /// The number of `JUMP` regions opened so far.
+    jumps: usize,
+    warnings: Vec<Warning>,
+}
+
+/// What is kept track of while reading a single file.
+struct Frame<'r> {
+    lines: Lines<Box<dyn BufRead + 'r>>,
+    ctx: TimContext,
+    name: String,
+    line_number: usize,
+    /// The format currently in use for the file.
+    mode: TimFormat,
+    /// The effects of the directives read so far.
+    state: TimState,
+    /// Directive and comment lines read since the last TOA.
+    directives: Vec<String>,
+}
+impl<'r> Frame<'r> {
+    fn new(
+        reader: Box<dyn BufRead + 'r>,
+        name: String,
+        mode: TimFormat,
+        state: TimState,
+    ) -> Self {
+        Self {
+            lines: reader.lines(),
+            ctx: TimContext::new(&name, 0),
+            name,
+            line_number: 0,
+            mode,
+            state,
+            directives: Vec::new(),
+        }
+    }
+}
+
+impl<'r> Toas<'r> {
+    pub(super) fn new(
+        reader: TimReader<'r>,
+        source: Box<dyn BufRead + 'r>,
+        name: &str,
+    ) -> Self {
+        let frame = Frame::new(
+            source,
+            name.to_string(),
+            reader.format,
+            TimState::default(),
+        );
+
+        Self {
+            reader,
+            stack: vec![frame],
+            seen: HashSet::from([name.to_string()]),
+            jumps: 0,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// The warnings gathered so far.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Takes out the warnings gathered so far, so that they do not pile up
+    /// while reading large amounts of data.
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// Acts on a line of the innermost file, giving back the line if it is
+    /// a TOA, along with what is needed to parse it.
+    fn read_line(
+        &mut self,
+        line: String,
+    ) -> Result<Option<Pending>, PsruError> {
+        let parts = line.split_whitespace().collect::<Vec<_>>();
+        let Some(frame) = self.stack.last_mut() else {
+            return Ok(None);
+        };
+
+        match classify(&parts) {
+            LineKind::Include(target) => {
+                let target = target.ok_or(PsruError::TimUnexpectedEOL(None))?;
+                self.include(target)?;
+            }
+            LineKind::Format => format_one(&mut frame.mode)?,
+            LineKind::Comment => frame.directives.push(line),
+            LineKind::Command => {
+                frame.state.command(&parts, &mut self.jumps)?;
+                frame.directives.push(line);
+            }
+            LineKind::Unsupported(word) => {
+                let kind = WarningKind::UnsupportedDirective(word.to_string());
+                self.warnings.push(frame.ctx.warning(kind));
+                frame.directives.push(line);
+            }
+            LineKind::Toa => {
+                if frame.mode == TimFormat::Auto {
+                    frame.mode = TimFormat::detect(&line);
+                }
+
+                return Ok(Some(Pending {
+                    line,
+                    ctx: frame.ctx.clone(),
+                    mode: frame.mode,
+                    state: frame.state,
+                    directives: std::mem::take(&mut frame.directives),
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Reads lines until the next one with a TOA.
+    fn next_pending(&mut self) -> Option<Result<Pending, PsruError>> {
+        loop {
+            let frame = self.stack.last_mut()?;
+
+            let line = match frame.lines.next() {
+                Some(Ok(line)) => line,
+                Some(Err(err)) => {
+                    self.stack.clear();
+                    return Some(Err(err.into()));
+                }
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+
+            frame.line_number += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            frame.ctx.line(frame.line_number);
+            let ctx = frame.ctx.clone();
+
+            match self.read_line(line) {
+                Ok(Some(pending)) => return Some(Ok(pending)),
+                Ok(None) => {}
+                Err(err) => {
+                    self.stack.clear();
+                    return Some(Err(err.set_tim_ctx(&ctx)));
+                }
+            }
+        }
+    }
+
+    /// Starts reading an included file, unless it is to be skipped.
+    fn include(&mut self, target: &str) -> Result<(), PsruError> {
+        let Some(parent) = self.stack.last() else {
+            return Ok(());
+        };
+
+        let resolver = self.reader.resolver;
+        let (included, source) = resolver.resolve(&parent.name, target)?;
+
+        let chain = || {
+            let names = self.stack.iter().map(|f| f.name.clone());
+            names.chain(std::iter::once(included.clone())).collect()
+        };
+        if self.stack.iter().any(|f| f.name == included) {
+            return Err(PsruError::TimIncludeCycle(None, chain()));
+        }
+        if self.stack.len() > self.reader.max_depth {
+            return Err(PsruError::TimIncludeTooDeep(None, chain()));
+        }
+        if self.reader.dedup && self.seen.contains(&included) {
+            let kind = WarningKind::RepeatedInclude(included);
+            self.warnings.push(parent.ctx.warning(kind));
+            return Ok(());
+        }
+
+        let state = parent.state;
+        self.seen.insert(included.clone());
+        self.stack.push(Frame::new(
+            source,
+            included,
+            self.reader.format,
+            state,
+        ));
+
+        Ok(())
+    }
+}
+
+impl Iterator for Toas<'_> {
+    type Item = Result<TOAInfo, PsruError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let pending = match self.next_pending()? {
+                Ok(pending) => pending,
+                Err(err) => return Some(Err(err)),
+            };
+
+            let (parsed, warnings) = pending.parse();
+            self.warnings.extend(warnings);
+
+            match parsed {
+                Ok(Parsed::Kept(toa_info)) => return Some(Ok(toa_info)),
+                Ok(Parsed::Dropped(directives)) => {
+                    // These go with the next TOA instead
+                    if let Some(frame) = self.stack.last_mut() {
+                        frame.directives = directives;
+                    }
+                }
+                Err(err) => {
+                    self.stack.clear();
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+/// Reads everything from `source`, in order.
+#[cfg(not(feature = "rayon"))]
+pub(super) fn read_all<'r>(
+    reader: &TimReader<'r>,
+    source: impl BufRead + 'r,
+    name: &str,
+) -> Result<(Vec<TOAInfo>, Vec<Warning>), PsruError> {
+    let mut toas = reader.toas(source, name);
+    let toa_infos = toas.by_ref().collect::<Result<Vec<_>, _>>()?;
+    Ok((toa_infos, toas.take_warnings()))
+}
+
+/// What a line of `.tim` data is, as far as can be told from the line
+/// alone.
+pub(super) enum LineKind<'a> {
+    /// An `INCLUDE`, with its target if there is one.
+    Include(Option<&'a str>),
+    /// A `FORMAT 1`, which switches to the Tempo2 format.
+    Format,
+    Comment,
+    /// A directive that acts on the TOAs after it.
+    Command,
+    /// A directive that is recognised, but left alone.
+    Unsupported(&'a str),
+    Toa,
+}
+
+/// Tells what a line is from its words, of which there is at least one.
+pub(super) fn classify<'a>(parts: &[&'a str]) -> LineKind<'a> {
+    match parts {
+        ["INCLUDE", rest @ ..] => LineKind::Include(rest.first().copied()),
+        ["FORMAT", "1", ..] => LineKind::Format,
+        [first, ..] if first.starts_with('#') => LineKind::Comment,
+        [first, ..] if is_command(first) => LineKind::Command,
+        [first, ..] if UNSUPPORTED_DIRECTIVES.contains(first) => {
+            LineKind::Unsupported(first)
+        }
+        _ => LineKind::Toa,
+    }
+}
+
+/// Acts on `FORMAT 1`, which fails if a file is read in another format.
+pub(super) fn format_one(mode: &mut TimFormat) -> Result<(), PsruError> {
+    if *mode == TimFormat::Auto {
+        *mode = TimFormat::Tempo2;
+    }
+    if *mode != TimFormat::Tempo2 {
+        return Err(PsruError::TimFormatDiscrepancy(
+            None,
+            String::from("Tempo2"),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Parses a TOA line, giving warnings for any oddities in it.
+pub(super) fn parse_toa(
+    line: &str,
+    mode: TimFormat,
+    ctx: &TimContext,
+) -> Result<(TOAInfo, Vec<Warning>), PsruError> {
+    let toa_info = match mode {
+        TimFormat::Tempo2 => {
+            let parts = line.split_whitespace().collect::<Vec<_>>();
+            TOAInfo::parse_tempo2(&parts)
+        }
+        other => TOAInfo::from_line(line, other),
+    }
+    .map_err(|err| err.set_tim_ctx(ctx))?;
+
+    let oddities = toa_info.oddities().into_iter();
+    let warnings = oddities.map(|kind| ctx.warning(kind)).collect();
+    Ok((toa_info, warnings))
+}
+
+/// A TOA line, yet to be parsed, with the effects of the directives before
+/// it.
+struct Pending {
+    line: String,
+    ctx: TimContext,
+    mode: TimFormat,
+    state: TimState,
+    directives: Vec<String>,
+}
+
+/// What became of a TOA line.
+enum Parsed {
+    Kept(TOAInfo),
+    /// The TOA was skipped or cut, leaving the directives before it.
+    Dropped(Vec<String>),
+}
+
+impl Pending {
+    /// Parses the line, giving warnings for any oddities in it.
+    fn parse(self) -> (Result<Parsed, PsruError>, Vec<Warning>) {
+        let (toa_info, warnings) =
+            match parse_toa(&self.line, self.mode, &self.ctx) {
+                Ok(parsed) => parsed,
+                Err(err) => return (Err(err), Vec::new()),
+            };
+
+        let parsed = match self.state.apply(toa_info) {
+            Ok(Some(mut toa_info)) => {
+                toa_info.directives = self.directives;
+                Parsed::Kept(toa_info)
+            }
+            Ok(None) => Parsed::Dropped(self.directives),
+            Err(err) => return (Err(err.set_tim_ctx(&self.ctx)), warnings),
+        };
+
+        (Ok(parsed), warnings)
+    }
+}
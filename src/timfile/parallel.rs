@@ -0,0 +1,367 @@
+//! Parsing `.tim` files in parallel, with `rayon`.
+
+use std::collections::HashSet;
+use std::io::BufRead;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+
+use rayon::{Scope, prelude::*};
+
+use super::directive::TimState;
+use super::iter::{LineKind, classify, format_one, parse_toa};
+use super::{TOAInfo, TimFormat, TimReader};
+use crate::{
+    error::{PsruError, TimContext},
+    warning::{Warning, WarningKind},
+};
+
+/// How many lines each thread parses at a time, at the least.
+const MIN_CHUNK: usize = 1024;
+
+/// A file, read into memory.
+struct Loaded {
+    name: String,
+    /// The lines that are not blank, with their numbers.
+    lines: Vec<(usize, String)>,
+    /// What became of the `INCLUDE`s among the lines, in order.
+    includes: Vec<Included>,
+    /// What stopped the rest of the file from being read.
+    failure: Option<PsruError>,
+}
+
+/// What became of an `INCLUDE`.
+enum Included {
+    /// The file was read, and has this index.
+    File(usize),
+    /// The file was left out, as it was read before.
+    Repeated(Warning),
+}
+
+/// Reads a file and all the files it includes, in the same order as
+/// [`Toas`](super::Toas) does. Each file is handed out to be parsed as soon
+/// as it has been read.
+struct Loader<'a, 'r, 's> {
+    reader: &'a TimReader<'r>,
+    scope: &'a Scope<'s>,
+    /// Where the parsed files go, by the order they were opened.
+    parsed: Sender<(usize, Vec<Step>)>,
+    /// Whether a parsed file has turned out to have an error.
+    failed: &'s AtomicBool,
+    /// The number of files opened so far.
+    opened: usize,
+    /// The names of all files read so far.
+    seen: HashSet<String>,
+    /// The names of the files being read, starting with the outermost.
+    chain: Vec<String>,
+    /// Whether reading has stopped, as there is no use going on.
+    stopped: bool,
+}
+impl<'r> Loader<'_, 'r, '_> {
+    /// Reads a file, and the files it includes as they come up, then hands
+    /// it out to be parsed.
+    fn load(&mut self, source: Box<dyn BufRead + 'r>, name: String) {
+        let index = self.opened;
+        self.opened += 1;
+        self.seen.insert(name.clone());
+        self.chain.push(name.clone());
+
+        let mut file = Loaded {
+            name,
+            lines: Vec::new(),
+            includes: Vec::new(),
+            failure: None,
+        };
+        self.read(&mut file, source);
+        self.chain.pop();
+
+        let format = self.reader.format;
+        let parsed = self.parsed.clone();
+        let failed = self.failed;
+        self.scope.spawn(move |_| {
+            _ = parsed.send((index, parse_file(file, format, failed)));
+        });
+    }
+
+    /// Reads the lines of a file, until its end or until reading stops.
+    fn read(&mut self, file: &mut Loaded, source: Box<dyn BufRead + 'r>) {
+        for (number, line) in source.lines().enumerate() {
+            // An error comes before anything still to be read
+            if self.stopped || self.failed.load(Ordering::Relaxed) {
+                self.stopped = true;
+                return;
+            }
+
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => return self.stop(file, err.into()),
+            };
+
+            let parts = line.split_whitespace().collect::<Vec<_>>();
+            if parts.is_empty() {
+                continue;
+            }
+            let LineKind::Include(target) = classify(&parts) else {
+                file.lines.push((number + 1, line));
+                continue;
+            };
+
+            let ctx = TimContext::new(&file.name, number + 1);
+            match self.include(target, &ctx) {
+                Ok(included) => file.includes.push(included),
+                Err(err) => return self.stop(file, err.set_tim_ctx(&ctx)),
+            }
+            file.lines.push((number + 1, line));
+        }
+    }
+
+    /// Stops reading at a failure in a file.
+    fn stop(&mut self, file: &mut Loaded, failure: PsruError) {
+        file.failure = Some(failure);
+        self.stopped = true;
+    }
+
+    /// Reads an included file, unless it is to be left out.
+    fn include(
+        &mut self,
+        target: Option<&str>,
+        ctx: &TimContext,
+    ) -> Result<Included, PsruError> {
+        let target = target.ok_or(PsruError::TimUnexpectedEOL(None))?;
+        let parent = self.chain.last().map_or("", String::as_str);
+        let (included, source) =
+            self.reader.resolver.resolve(parent, target)?;
+
+        let chain = || {
+            let names = self.chain.iter().cloned();
+            names.chain(std::iter::once(included.clone())).collect()
+        };
+        if self.chain.contains(&included) {
+            return Err(PsruError::TimIncludeCycle(None, chain()));
+        }
+        if self.chain.len() > self.reader.max_depth {
+            return Err(PsruError::TimIncludeTooDeep(None, chain()));
+        }
+        if self.reader.dedup && self.seen.contains(&included) {
+            let kind = WarningKind::RepeatedInclude(included);
+            return Ok(Included::Repeated(ctx.warning(kind)));
+        }
+
+        let index = self.opened;
+        self.load(source, included);
+        Ok(Included::File(index))
+    }
+}
+
+/// A line of a file, as far as it can be made out from the file alone.
+enum Step {
+    Comment(String),
+    /// A directive that acts on the TOAs after it.
+    Command(String, TimContext),
+    /// A directive that is recognised, but left alone.
+    Unsupported(String, Warning),
+    Include(Included),
+    Toa(Toa),
+    /// What stopped the rest of the file from being read or made out.
+    Failure(PsruError),
+}
+
+/// A TOA line, parsed unless it comes after an error.
+struct Toa {
+    line: String,
+    ctx: TimContext,
+    mode: TimFormat,
+    parsed: Option<Result<(TOAInfo, Vec<Warning>), PsruError>>,
+}
+
+/// Makes out the lines of a file, and parses its TOAs. Sets `failed` if the
+/// file has an error.
+fn parse_file(
+    file: Loaded,
+    format: TimFormat,
+    failed: &AtomicBool,
+) -> Vec<Step> {
+    let mut mode = format;
+    let mut resolved = file.includes.into_iter();
+    let mut steps = Vec::with_capacity(file.lines.len() + 1);
+
+    for (number, line) in file.lines {
+        let ctx = TimContext::new(&file.name, number);
+        let parts = line.split_whitespace().collect::<Vec<_>>();
+        let step = match classify(&parts) {
+            LineKind::Include(_) => match resolved.next() {
+                Some(included) => Step::Include(included),
+                None => continue,
+            },
+            LineKind::Format => match format_one(&mut mode) {
+                Ok(()) => continue,
+                Err(err) => {
+                    steps.push(Step::Failure(err.set_tim_ctx(&ctx)));
+                    break;
+                }
+            },
+            LineKind::Comment => Step::Comment(line),
+            LineKind::Command => Step::Command(line, ctx),
+            LineKind::Unsupported(word) => {
+                let kind = WarningKind::UnsupportedDirective(word.to_string());
+                Step::Unsupported(line, ctx.warning(kind))
+            }
+            LineKind::Toa => {
+                if mode == TimFormat::Auto {
+                    mode = TimFormat::detect(&line);
+                }
+                Step::Toa(Toa {
+                    line,
+                    ctx,
+                    mode,
+                    parsed: None,
+                })
+            }
+        };
+        steps.push(step);
+    }
+
+    steps.extend(file.failure.map(Step::Failure));
+    if parse_toas(&mut steps) {
+        failed.store(true, Ordering::Relaxed);
+    }
+    steps
+}
+
+/// Parses the TOAs of a file in parallel, in chunks of lines. The ones after
+/// the first error of the file are left alone, since they are not needed.
+/// Gives whether there is an error.
+fn parse_toas(steps: &mut [Step]) -> bool {
+    let first_error = AtomicUsize::new(usize::MAX);
+
+    steps
+        .par_iter_mut()
+        .enumerate()
+        .with_min_len(MIN_CHUNK)
+        .for_each(|(index, step)| match step {
+            Step::Toa(toa) if index < first_error.load(Ordering::Relaxed) => {
+                let parsed = parse_toa(&toa.line, toa.mode, &toa.ctx);
+                if parsed.is_err() {
+                    first_error.fetch_min(index, Ordering::Relaxed);
+                }
+                toa.parsed = Some(parsed);
+            }
+            Step::Failure(_) => {
+                first_error.fetch_min(index, Ordering::Relaxed);
+            }
+            _ => {}
+        });
+
+    first_error.into_inner() != usize::MAX
+}
+
+/// Puts together the parsed files in order, acting on the directives.
+struct Merger {
+    files: Vec<Vec<Step>>,
+    /// The number of `JUMP` regions opened so far.
+    jumps: usize,
+    toa_infos: Vec<TOAInfo>,
+    warnings: Vec<Warning>,
+}
+impl Merger {
+    /// Adds the TOAs of a file and those it includes, starting with `state`,
+    /// and stopping at the first error.
+    fn merge(
+        &mut self,
+        index: usize,
+        mut state: TimState,
+    ) -> Result<(), PsruError> {
+        let steps = std::mem::take(&mut self.files[index]);
+        // Directive and comment lines read since the last TOA
+        let mut directives = Vec::new();
+
+        for step in steps {
+            match step {
+                Step::Comment(line) => directives.push(line),
+                Step::Command(line, ctx) => {
+                    let parts = line.split_whitespace().collect::<Vec<_>>();
+                    state
+                        .command(&parts, &mut self.jumps)
+                        .map_err(|err| err.set_tim_ctx(&ctx))?;
+                    directives.push(line);
+                }
+                Step::Unsupported(line, warning) => {
+                    self.warnings.push(warning);
+                    directives.push(line);
+                }
+                Step::Include(Included::File(child)) => {
+                    self.merge(child, state)?;
+                }
+                Step::Include(Included::Repeated(warning)) => {
+                    self.warnings.push(warning);
+                }
+                Step::Toa(toa) => {
+                    let (toa_info, warnings) =
+                        toa.parsed.unwrap_or_else(|| {
+                            parse_toa(&toa.line, toa.mode, &toa.ctx)
+                        })?;
+                    self.warnings.extend(warnings);
+
+                    let kept = state
+                        .apply(toa_info)
+                        .map_err(|err| err.set_tim_ctx(&toa.ctx))?;
+                    // Skipped and cut TOAs leave the directives to the next
+                    if let Some(mut toa_info) = kept {
+                        toa_info.directives = std::mem::take(&mut directives);
+                        self.toa_infos.push(toa_info);
+                    }
+                }
+                Step::Failure(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads everything from `source`. The files are read in order, on the
+/// calling thread, and each is parsed in parallel with the others once it has
+/// been read, large ones in chunks of lines. Nothing more is read once a file
+/// turns out to have an error. Last, the directives are acted on in order, so
+/// that the TOAs, warnings, and the first error come out as they would from
+/// reading in order.
+pub(super) fn read_all<'r>(
+    reader: &TimReader<'r>,
+    source: impl BufRead + 'r,
+    name: &str,
+) -> Result<(Vec<TOAInfo>, Vec<Warning>), PsruError> {
+    let (sender, receiver) = mpsc::channel();
+    let failed = AtomicBool::new(false);
+
+    let opened = rayon::in_place_scope(|scope| {
+        let mut loader = Loader {
+            reader,
+            scope,
+            parsed: sender,
+            failed: &failed,
+            opened: 0,
+            seen: HashSet::new(),
+            chain: Vec::new(),
+            stopped: false,
+        };
+        loader.load(Box::new(source), name.to_string());
+        loader.opened
+    });
+
+    let mut files = Vec::new();
+    files.resize_with(opened, Vec::new);
+    for (index, steps) in receiver {
+        files[index] = steps;
+    }
+
+    // Files cut short by an error elsewhere are never reached, as the error
+    // comes before the point where reading stopped
+    let mut merger = Merger {
+        files,
+        jumps: 0,
+        toa_infos: Vec::new(),
+        warnings: Vec::new(),
+    };
+    merger.merge(0, TimState::default())?;
+
+    Ok((merger.toa_infos, merger.warnings))
+}
@@ -0,0 +1,241 @@
+//! Choosing TOAs by their values, flags, and sites.
+
+use std::str::FromStr;
+
+use super::TOAInfo;
+use crate::error::PsruError;
+use crate::parse_tools::parse_f64;
+
+/// A single condition on a TOA.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// The MJD is within the (inclusive) range.
+    Mjd(f64, f64),
+    /// The frequency (MHz) is within the (inclusive) range.
+    Freq(f64, f64),
+    /// The uncertainty (µs) is within the (inclusive) range.
+    Error(f64, f64),
+    /// The site id is the given one.
+    Tel(String),
+    /// The file name is the given one.
+    Name(String),
+    /// The flag (without `-`) is written as the given value.
+    Flag(String, String),
+    /// The TOA is marked as bad.
+    Bad,
+}
+impl Condition {
+    /// Whether the TOA meets the condition.
+    pub fn matches(&self, toa: &TOAInfo) -> bool {
+        match self {
+            Self::Mjd(min, max) => (*min..=*max).contains(&toa.mjd.to_f64()),
+            Self::Freq(min, max) => (*min..=*max).contains(&toa.frequency),
+            Self::Error(min, max) => (*min..=*max).contains(&toa.mjd_error),
+            Self::Tel(site) => toa.site_id == *site,
+            Self::Name(name) => toa.file == *name,
+            Self::Flag(key, value) => toa.flag_str(key) == Some(value),
+            Self::Bad => toa.is_bad,
+        }
+    }
+}
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mjd(min, max) => {
+                write!(f, "MJD {} {}", bound(*min), bound(*max))
+            }
+            Self::Freq(min, max) => {
+                write!(f, "FREQ {} {}", bound(*min), bound(*max))
+            }
+            Self::Error(min, max) => {
+                write!(f, "ERR {} {}", bound(*min), bound(*max))
+            }
+            Self::Tel(site) => write!(f, "TEL {site}"),
+            Self::Name(name) => write!(f, "NAME {name}"),
+            Self::Flag(key, value) => write!(f, "-{key} {value}"),
+            Self::Bad => write!(f, "BAD"),
+        }
+    }
+}
+
+/// A set of conditions that TOAs must all meet (or all not meet, for those
+/// that are negated) to be selected.
+///
+/// Selectors can be read from text, with a syntax much like the `-select`
+/// files of Tempo2 and the selectors of `JUMP`s in `.par` files:
+///
+/// | Term              | Selects TOAs...                                 |
+/// |-------------------|-------------------------------------------------|
+/// | `MJD min max`     | with MJDs in the range                          |
+/// | `FREQ min max`    | with frequencies (MHz) in the range             |
+/// | `ERR min max`     | with uncertainties (µs) in the range            |
+/// | `TEL site`        | from the site                                   |
+/// | `NAME name`       | with the file name                              |
+/// | `-flag value`     | with the flag written as the value              |
+/// | `BAD`             | that are marked as bad                          |
+/// | `NOT` or `REJECT` | that do not meet the term after it              |
+/// | `PASS`            | that meet the term after it (for compatibility) |
+///
+/// Ranges are inclusive, and either bound may be `*` to leave it open.
+/// Keywords may be written in any case, and an empty selector selects all
+/// TOAs.
+///
+/// ```
+/// # use psrutils::timfile::{Selector, TOAInfo};
+/// let toas = [
+///     "a 1400.0 56500.5 1.0 ao -be PUPPI",
+///     "C a 1400.0 56501.5 1.0 ao -be PUPPI",
+///     "a 820.0 56502.5 1.0 gbt -be PUPPI",
+///     "a 1400.0 55000.5 1.0 ao -be PUPPI",
+/// ]
+/// .map(|l| TOAInfo::from_line_tempo2(l).unwrap());
+///
+/// let selector = "-be PUPPI FREQ 1300 1500 MJD 56000 * NOT BAD"
+///     .parse::<Selector>()
+///     .unwrap();
+///
+/// assert_eq!(selector.indices(&toas), [0]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selector {
+    /// The conditions, and whether each is negated.
+    terms: Vec<(bool, Condition)>,
+}
+impl Selector {
+    /// A selector that selects all TOAs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a condition that TOAs must meet.
+    #[must_use]
+    pub fn with(mut self, condition: Condition) -> Self {
+        self.terms.push((false, condition));
+        self
+    }
+
+    /// Adds a condition that TOAs must not meet.
+    #[must_use]
+    pub fn without(mut self, condition: Condition) -> Self {
+        self.terms.push((true, condition));
+        self
+    }
+
+    /// The conditions, and whether each is negated.
+    pub fn terms(&self) -> &[(bool, Condition)] {
+        &self.terms
+    }
+
+    /// Whether the TOA is selected.
+    pub fn matches(&self, toa: &TOAInfo) -> bool {
+        self.terms
+            .iter()
+            .all(|(negated, condition)| condition.matches(toa) != *negated)
+    }
+
+    /// The selected TOAs, in order.
+    pub fn filter<'a>(
+        &self,
+        toas: &'a [TOAInfo],
+    ) -> impl Iterator<Item = &'a TOAInfo> {
+        toas.iter().filter(|toa| self.matches(toa))
+    }
+
+    /// The indices of the selected TOAs, in order.
+    pub fn indices(&self, toas: &[TOAInfo]) -> Vec<usize> {
+        toas.iter()
+            .enumerate()
+            .filter(|(_, toa)| self.matches(toa))
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+impl From<Condition> for Selector {
+    fn from(condition: Condition) -> Self {
+        Self::new().with(condition)
+    }
+}
+impl FromStr for Selector {
+    type Err = PsruError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || PsruError::BadSelector(s.to_string());
+        let mut parts = s.split_whitespace();
+        let mut selector = Self::new();
+        let mut negated = false;
+
+        while let Some(part) = parts.next() {
+            let mut next = || parts.next().ok_or_else(error);
+            let condition = match part.to_ascii_uppercase().as_str() {
+                "NOT" | "REJECT" => {
+                    negated = !negated;
+                    continue;
+                }
+                "PASS" => continue,
+                "MJD" => Condition::Mjd(lower(next()?)?, upper(next()?)?),
+                "FREQ" => Condition::Freq(lower(next()?)?, upper(next()?)?),
+                "ERR" => Condition::Error(lower(next()?)?, upper(next()?)?),
+                "TEL" => Condition::Tel(next()?.to_string()),
+                "NAME" => Condition::Name(next()?.to_string()),
+                "BAD" => Condition::Bad,
+                _ => match part.strip_prefix('-') {
+                    Some(key) if !key.is_empty() => {
+                        Condition::Flag(key.to_string(), next()?.to_string())
+                    }
+                    _ => return Err(error()),
+                },
+            };
+
+            selector.terms.push((negated, condition));
+            negated = false;
+        }
+
+        if negated {
+            return Err(error());
+        }
+
+        Ok(selector)
+    }
+}
+impl std::fmt::Display for Selector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, (negated, condition)) in self.terms.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            if *negated {
+                write!(f, "NOT ")?;
+            }
+            write!(f, "{condition}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The lower bound of a range, where `*` leaves it open.
+fn lower(text: &str) -> Result<f64, PsruError> {
+    if text == "*" {
+        Ok(f64::NEG_INFINITY)
+    } else {
+        parse_f64(text)
+    }
+}
+
+/// The upper bound of a range, where `*` leaves it open.
+fn upper(text: &str) -> Result<f64, PsruError> {
+    if text == "*" {
+        Ok(f64::INFINITY)
+    } else {
+        parse_f64(text)
+    }
+}
+
+/// The text of a bound of a range.
+fn bound(value: f64) -> String {
+    if value.is_infinite() {
+        String::from("*")
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,299 @@
+//! Overviews of sets of TOAs.
+
+use std::collections::BTreeMap;
+
+use super::TOAInfo;
+use crate::data_types::Mjd;
+
+/// How many of the largest gaps are kept.
+const GAPS: usize = 5;
+
+/// Statistics describing a set of TOAs, e.g. as read by
+/// [`read_tim`](super::read_tim).
+///
+/// TOAs marked as bad are counted, but left out of all other statistics.
+/// Uncertainties that are not positive are left out of the uncertainty
+/// statistics.
+///
+/// ```
+/// # use psrutils::timfile::{Summary, TOAInfo};
+/// let toas = [
+///     "a 1400 55000.5 1.0 ao -be PUPPI -fe L-wide",
+///     "a 430 55010.5 2.0 ao -be PUPPI -fe 430",
+///     "C a 1400 55011.5 1.0 gbt -be GUPPI -fe L-wide",
+///     "a 1400 55030.5 1.0 gbt -be GUPPI -fe L-wide",
+/// ]
+/// .map(|l| TOAInfo::from_line_tempo2(l).unwrap());
+///
+/// let summary = Summary::by_flag(&toas, "fe");
+/// assert_eq!(summary.count, 4);
+/// assert_eq!(summary.bad, 1);
+/// assert_eq!(summary.median_error, Some(1.0));
+/// assert_eq!(summary.cadence, Some(15.0));
+/// assert_eq!(summary.groups[0].0.as_deref(), Some("430"));
+/// assert_eq!(summary.weighted_error, Some(1.0 / 1.5));
+///
+/// let text = summary.to_string();
+/// assert!(text.starts_with("TOAs            4 (1 bad)\n"));
+/// assert!(text.contains("\nUncertainties   1.000 µs median, 0.667 µs weighted\n"));
+/// assert!(text.contains("\nSites           ao: 2, gbt: 1\n"));
+/// assert!(text.contains("\n430         1     0   55010.500"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    /// The number of TOAs.
+    pub count: usize,
+    /// The number of TOAs marked as bad.
+    pub bad: usize,
+
+    /// The earliest and latest MJDs.
+    pub span: Option<(Mjd, Mjd)>,
+    /// The lowest and highest frequencies (MHz).
+    pub frequencies: Option<(f64, f64)>,
+
+    /// The median uncertainty (µs).
+    pub median_error: Option<f64>,
+    /// The uncertainty of the mean weighted by the inverse variances (µs),
+    /// i.e. `1 / √(Σ σ⁻²)`. This is how well the TOAs measure a constant
+    /// offset together.
+    pub weighted_error: Option<f64>,
+
+    /// The number of distinct days with TOAs.
+    pub days: usize,
+    /// The median time between days with TOAs (days).
+    pub cadence: Option<f64>,
+    /// The largest gaps between consecutive TOAs, largest first.
+    pub gaps: Vec<(Mjd, Mjd)>,
+
+    /// The number of TOAs from each site, by site id.
+    pub sites: Vec<(String, usize)>,
+    /// The number of TOAs from each backend, by the `be` flag.
+    pub backends: Vec<(String, usize)>,
+
+    /// The flag the groups are made by, if any.
+    pub group_key: Option<String>,
+    /// Statistics of the TOAs with each value of the flag, in order of the
+    /// values. TOAs without the flag are in the group `None`, which is last.
+    pub groups: Vec<(Option<String>, Self)>,
+}
+impl Summary {
+    /// Computes the statistics of a set of TOAs.
+    pub fn new(toas: &[TOAInfo]) -> Self {
+        Self::of(toas.iter())
+    }
+
+    /// Computes the statistics of a set of TOAs, and of the TOAs with each
+    /// value of the flag `key` on their own.
+    pub fn by_flag(toas: &[TOAInfo], key: &str) -> Self {
+        let mut values = BTreeMap::<Option<&str>, Vec<&TOAInfo>>::new();
+        for toa in toas {
+            values.entry(toa.flag_str(key)).or_default().push(toa);
+        }
+
+        // `None` sorts first, but reads better last
+        let mut groups = values
+            .into_iter()
+            .map(|(value, toas)| {
+                (value.map(str::to_string), Self::of(toas.into_iter()))
+            })
+            .collect::<Vec<_>>();
+        if groups.first().is_some_and(|(value, _)| value.is_none()) {
+            groups.rotate_left(1);
+        }
+
+        Self {
+            group_key: Some(key.to_string()),
+            groups,
+            ..Self::new(toas)
+        }
+    }
+
+    fn of<'a>(toas: impl Iterator<Item = &'a TOAInfo>) -> Self {
+        let (bad, good): (Vec<_>, Vec<_>) = toas.partition(|t| t.is_bad);
+
+        let mut mjds = good.iter().map(|t| t.mjd).collect::<Vec<_>>();
+        mjds.sort_unstable();
+
+        let mut errors = good
+            .iter()
+            .map(|t| t.mjd_error)
+            .filter(|e| *e > 0.0)
+            .collect::<Vec<_>>();
+        errors.sort_by(f64::total_cmp);
+        let weighted_error = (!errors.is_empty()).then(|| {
+            let weights = errors.iter().map(|e| e.powi(-2)).sum::<f64>();
+            weights.sqrt().recip()
+        });
+
+        let frequencies =
+            good.iter().map(|t| t.frequency).fold(None, |r, f| {
+                let (min, max) = r.unwrap_or((f, f));
+                Some((f64::min(min, f), f64::max(max, f)))
+            });
+
+        let mut days = mjds.iter().map(Mjd::int).collect::<Vec<_>>();
+        days.dedup();
+        let spacings = days
+            .windows(2)
+            .map(|w| f64::from(w[1] - w[0]))
+            .collect::<Vec<_>>();
+
+        let mut gaps =
+            mjds.windows(2).map(|w| (w[0], w[1])).collect::<Vec<_>>();
+        gaps.sort_by(|a, b| length(*b).total_cmp(&length(*a)));
+        gaps.truncate(GAPS);
+
+        Self {
+            count: good.len() + bad.len(),
+            bad: bad.len(),
+            span: mjds.first().zip(mjds.last()).map(|(a, b)| (*a, *b)),
+            frequencies,
+            median_error: median(&errors),
+            weighted_error,
+            days: days.len(),
+            cadence: median(&spacings),
+            gaps,
+            sites: counts(good.iter().map(|t| t.site_id.as_str())),
+            backends: counts(good.iter().filter_map(|t| t.flag_str("be"))),
+            group_key: None,
+            groups: Vec::new(),
+        }
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{:<16}{} ({} bad)", "TOAs", self.count, self.bad)?;
+        if let Some((first, last)) = self.span {
+            writeln!(
+                f,
+                "{:<16}{:.3} - {:.3} ({:.1} days)",
+                "MJDs",
+                first.to_f64(),
+                last.to_f64(),
+                length((first, last)),
+            )?;
+        }
+        if let Some((min, max)) = self.frequencies {
+            writeln!(f, "{:<16}{min} - {max} MHz", "Frequencies")?;
+        }
+        if let (Some(median), Some(weighted)) =
+            (self.median_error, self.weighted_error)
+        {
+            writeln!(
+                f,
+                "{:<16}{median:.3} µs median, {weighted:.3} µs weighted",
+                "Uncertainties",
+            )?;
+        }
+        write!(f, "{:<16}{}", "Days", self.days)?;
+        if let Some(cadence) = self.cadence {
+            write!(f, ", every {cadence:.1} days (median)")?;
+        }
+        writeln!(f)?;
+        for (i, gap) in self.gaps.iter().enumerate() {
+            writeln!(
+                f,
+                "{:<16}{:.3} - {:.3} ({:.1} days)",
+                if i == 0 { "Largest gaps" } else { "" },
+                gap.0.to_f64(),
+                gap.1.to_f64(),
+                length(*gap),
+            )?;
+        }
+        writeln!(f, "{:<16}{}", "Sites", list(&self.sites))?;
+        writeln!(f, "{:<16}{}", "Backends", list(&self.backends))?;
+
+        let Some(key) = &self.group_key else {
+            return Ok(());
+        };
+
+        let width = self
+            .groups
+            .iter()
+            .filter_map(|(value, _)| value.as_ref().map(String::len))
+            .chain([key.len() + 1, 6])
+            .max()
+            .unwrap_or_default();
+
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<width$} {:>6} {:>5} {:>11} {:>11} {:>9} {:>9} {:>10}",
+            format!("-{key}"),
+            "TOAs",
+            "Bad",
+            "First MJD",
+            "Last MJD",
+            "Min MHz",
+            "Max MHz",
+            "Median µs",
+        )?;
+        for (value, group) in &self.groups {
+            let span = group.span.map(|(a, b)| (a.to_f64(), b.to_f64()));
+            let first = decimals(span.map(|s| s.0), 3);
+            let last = decimals(span.map(|s| s.1), 3);
+            let min = decimals(group.frequencies.map(|f| f.0), 1);
+            let max = decimals(group.frequencies.map(|f| f.1), 1);
+            writeln!(
+                f,
+                "{:<width$} {:>6} {:>5} {first:>11} {last:>11} {min:>9} \
+                 {max:>9} {:>10}",
+                value.as_deref().unwrap_or("(none)"),
+                group.count,
+                group.bad,
+                decimals(group.median_error, 3),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The length of a gap, in days.
+fn length((from, to): (Mjd, Mjd)) -> f64 {
+    (to - from).as_days()
+}
+
+/// A value with a fixed number of decimals, or `-` if there is none.
+fn decimals(value: Option<f64>, precision: usize) -> String {
+    value.map_or_else(|| String::from("-"), |v| format!("{v:.precision$}"))
+}
+
+/// The median of sorted values.
+fn median(sorted: &[f64]) -> Option<f64> {
+    let middle = sorted.len() / 2;
+    match sorted.len() {
+        0 => None,
+        n if n % 2 == 0 => {
+            Some(f64::midpoint(sorted[middle - 1], sorted[middle]))
+        }
+        _ => Some(sorted[middle]),
+    }
+}
+
+/// The number of times each value occurs, in order of the values.
+fn counts<'a>(values: impl Iterator<Item = &'a str>) -> Vec<(String, usize)> {
+    let mut counts = BTreeMap::<&str, usize>::new();
+    for value in values {
+        *counts.entry(value).or_default() += 1;
+    }
+
+    counts
+        .into_iter()
+        .map(|(v, n)| (v.to_string(), n))
+        .collect()
+}
+
+/// Counts as a list, like `ao: 12, gbt: 3`.
+fn list(counts: &[(String, usize)]) -> String {
+    if counts.is_empty() {
+        return String::from("-");
+    }
+
+    counts
+        .iter()
+        .map(|(value, count)| format!("{value}: {count}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,372 @@
+//! A column-wise store of TOAs, for large datasets.
+
+use std::{collections::HashMap, sync::Arc};
+
+use super::{Flag, TOAInfo, TimFormat};
+use crate::data_types::Mjd;
+
+/// TOAs stored column by column, rather than one `TOAInfo` at a time.
+///
+/// File names, site ids, flag keys, and the text of flag values are
+/// interned, so that each distinct string is only stored once. Comments and
+/// directives, which are rare, are only stored for the TOAs that have them.
+///
+/// ```
+/// # use psrutils::timfile::{FlagRef, TOAInfo, ToaTable};
+/// let lines = [
+///     "a 1400.0 55000.5 1.0 ao -fe L-wide -snr 12",
+///     "a 430.0 55001.5 2.0 ao -fe 430",
+///     "b 1400.0 55002.5 1.5 gbt -fe L-wide",
+/// ];
+/// let table = lines
+///     .iter()
+///     .map(|l| TOAInfo::from_line_tempo2(l).unwrap())
+///     .collect::<ToaTable>();
+///
+/// assert_eq!(table.len(), 3);
+/// assert_eq!(table.errors(), [1.0, 2.0, 1.5]);
+/// assert_eq!(table.sites().collect::<Vec<_>>(), ["ao", "ao", "gbt"]);
+/// assert_eq!(table.flag(0, "fe"), Some(FlagRef::String("L-wide")));
+/// assert_eq!(table.flag(2, "snr"), None);
+///
+/// let toas = table.to_toas();
+/// assert_eq!(toas[1], TOAInfo::from_line_tempo2(lines[1]).unwrap());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ToaTable {
+    strings: Interner,
+
+    is_bad: Vec<bool>,
+    files: Vec<u32>,
+    frequencies: Vec<f64>,
+    mjds: Vec<Mjd>,
+    errors: Vec<f64>,
+    sites: Vec<u32>,
+    formats: Vec<TimFormat>,
+
+    /// Flag columns, by the interned key, in the order first seen.
+    flags: Vec<(u32, Vec<Option<FlagCell>>)>,
+    /// Positions in `flags`, by the interned key.
+    flag_positions: HashMap<u32, usize>,
+
+    /// Comments of the TOAs that have them, by index.
+    comments: Vec<(usize, String)>,
+    /// Directives of the TOAs that have them, by index.
+    directives: Vec<(usize, Vec<String>)>,
+}
+
+/// A flag value as stored in a [`ToaTable`], with the text interned.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FlagCell {
+    Int(i64, u32),
+    Double(f64, u32),
+    String(u32),
+}
+
+/// A flag value borrowed from a [`ToaTable`]. See [`Flag`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlagRef<'a> {
+    /// Integers that fit in `i64`, and the text.
+    Int(i64, &'a str),
+    /// Other values that can be cast to `f64`, and the text.
+    Double(f64, &'a str),
+    /// Anything that could not be cast to a number.
+    String(&'a str),
+}
+impl<'a> FlagRef<'a> {
+    /// The text of the value, as written.
+    pub const fn text(&self) -> &'a str {
+        match self {
+            Self::Int(_, text) | Self::Double(_, text) | Self::String(text) => {
+                text
+            }
+        }
+    }
+
+    /// The value, if it is an integer.
+    pub const fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Int(value, _) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value, if it is a number. Integers beyond 2^53 lose precision.
+    #[allow(clippy::cast_precision_loss)]
+    pub const fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Int(value, _) => Some(*value as f64),
+            Self::Double(value, _) => Some(*value),
+            Self::String(_) => None,
+        }
+    }
+
+    /// Makes an owned copy of the value.
+    pub fn to_flag(&self) -> Flag {
+        match self {
+            Self::Int(value, text) => Flag::Int(*value, (*text).to_string()),
+            Self::Double(value, text) => {
+                Flag::Double(*value, (*text).to_string())
+            }
+            Self::String(text) => Flag::String((*text).to_string()),
+        }
+    }
+}
+
+impl ToaTable {
+    /// An empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of TOAs.
+    pub const fn len(&self) -> usize {
+        self.mjds.len()
+    }
+
+    /// Whether there are no TOAs.
+    pub const fn is_empty(&self) -> bool {
+        self.mjds.is_empty()
+    }
+
+    /// Adds a TOA at the end.
+    pub fn push(&mut self, toa: TOAInfo) {
+        let index = self.len();
+
+        self.is_bad.push(toa.is_bad);
+        self.files.push(self.strings.intern(&toa.file));
+        self.frequencies.push(toa.frequency);
+        self.mjds.push(toa.mjd);
+        self.errors.push(toa.mjd_error);
+        self.sites.push(self.strings.intern(&toa.site_id));
+        self.formats.push(toa.format);
+
+        for (_, column) in &mut self.flags {
+            column.push(None);
+        }
+        for (key, value) in toa.flags {
+            let text = self.strings.intern(value.text());
+            let cell = match value {
+                Flag::Int(value, _) => FlagCell::Int(value, text),
+                Flag::Double(value, _) => FlagCell::Double(value, text),
+                Flag::String(_) => FlagCell::String(text),
+            };
+            self.flag_column(&key)[index] = Some(cell);
+        }
+
+        if !toa.comment.is_empty() {
+            self.comments.push((index, toa.comment));
+        }
+        if !toa.directives.is_empty() {
+            self.directives.push((index, toa.directives));
+        }
+    }
+
+    /// Makes a `TOAInfo` of the TOA at `index`.
+    pub fn get(&self, index: usize) -> Option<TOAInfo> {
+        if index >= self.len() {
+            return None;
+        }
+
+        let flags = self
+            .flags
+            .iter()
+            .filter_map(|(key, column)| {
+                let value = self.flag_ref(column[index]?).to_flag();
+                Some((self.strings.get(*key).to_string(), value))
+            })
+            .collect();
+
+        Some(TOAInfo {
+            is_bad: self.is_bad[index],
+            file: self.file(index).to_string(),
+            frequency: self.frequencies[index],
+            mjd: self.mjds[index],
+            mjd_error: self.errors[index],
+            site_id: self.site(index).to_string(),
+            comment: sparse(&self.comments, index).cloned().unwrap_or_default(),
+            flags,
+            format: self.formats[index],
+            directives: sparse(&self.directives, index)
+                .cloned()
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Makes `TOAInfo`s of all TOAs.
+    pub fn to_toas(&self) -> Vec<TOAInfo> {
+        (0..self.len()).filter_map(|i| self.get(i)).collect()
+    }
+
+    /// Whether each TOA is marked as bad.
+    pub fn bad(&self) -> &[bool] {
+        &self.is_bad
+    }
+
+    /// The observation frequencies.
+    pub fn frequencies(&self) -> &[f64] {
+        &self.frequencies
+    }
+
+    /// The date-times.
+    pub fn mjds(&self) -> &[Mjd] {
+        &self.mjds
+    }
+
+    /// The errors in MJD.
+    pub fn errors(&self) -> &[f64] {
+        &self.errors
+    }
+
+    /// The formats of the lines the TOAs were read from.
+    pub fn formats(&self) -> &[TimFormat] {
+        &self.formats
+    }
+
+    /// The file name of the TOA at `index`.
+    ///
+    /// # Panics
+    /// `panic`s if `index` is out of bounds.
+    pub fn file(&self, index: usize) -> &str {
+        self.strings.get(self.files[index])
+    }
+
+    /// The file names of all TOAs.
+    pub fn files(&self) -> impl Iterator<Item = &str> {
+        self.files.iter().map(|id| self.strings.get(*id))
+    }
+
+    /// The site id of the TOA at `index`.
+    ///
+    /// # Panics
+    /// `panic`s if `index` is out of bounds.
+    pub fn site(&self, index: usize) -> &str {
+        self.strings.get(self.sites[index])
+    }
+
+    /// The site ids of all TOAs.
+    pub fn sites(&self) -> impl Iterator<Item = &str> {
+        self.sites.iter().map(|id| self.strings.get(*id))
+    }
+
+    /// The comment of the TOA at `index`, if it has one.
+    pub fn comment(&self, index: usize) -> Option<&str> {
+        sparse(&self.comments, index).map(String::as_str)
+    }
+
+    /// The keys of all flags found on any TOA, in the order first seen.
+    pub fn flag_keys(&self) -> impl Iterator<Item = &str> {
+        self.flags.iter().map(|(key, _)| self.strings.get(*key))
+    }
+
+    /// The value of a flag of the TOA at `index`, if it has it.
+    pub fn flag(&self, index: usize, key: &str) -> Option<FlagRef<'_>> {
+        let cell = self.column(key)?.get(index).copied().flatten()?;
+        Some(self.flag_ref(cell))
+    }
+
+    /// The values of a flag for all TOAs, or `None` if no TOA has it.
+    pub fn flag_values(
+        &self,
+        key: &str,
+    ) -> Option<impl Iterator<Item = Option<FlagRef<'_>>>> {
+        let column = self.column(key)?;
+        Some(column.iter().map(|cell| cell.map(|c| self.flag_ref(c))))
+    }
+
+    /// The indices of the TOAs whose flag `key` is written as `value`.
+    pub fn with_flag(&self, key: &str, value: &str) -> Vec<usize> {
+        let Some(values) = self.flag_values(key) else {
+            return Vec::new();
+        };
+
+        values
+            .enumerate()
+            .filter(|(_, v)| v.is_some_and(|v| v.text() == value))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn flag_ref(&self, cell: FlagCell) -> FlagRef<'_> {
+        match cell {
+            FlagCell::Int(value, id) => {
+                FlagRef::Int(value, self.strings.get(id))
+            }
+            FlagCell::Double(value, id) => {
+                FlagRef::Double(value, self.strings.get(id))
+            }
+            FlagCell::String(id) => FlagRef::String(self.strings.get(id)),
+        }
+    }
+
+    /// The column of a flag, if any TOA has it.
+    fn column(&self, key: &str) -> Option<&[Option<FlagCell>]> {
+        let id = self.strings.find(key)?;
+        let position = *self.flag_positions.get(&id)?;
+        Some(&self.flags[position].1)
+    }
+
+    /// The column of a flag, made and filled with `None` if new.
+    fn flag_column(&mut self, key: &str) -> &mut Vec<Option<FlagCell>> {
+        let id = self.strings.intern(key);
+        let position = *self.flag_positions.entry(id).or_insert_with(|| {
+            self.flags.push((id, vec![None; self.mjds.len()]));
+            self.flags.len() - 1
+        });
+
+        &mut self.flags[position].1
+    }
+}
+
+impl FromIterator<TOAInfo> for ToaTable {
+    fn from_iter<T: IntoIterator<Item = TOAInfo>>(iter: T) -> Self {
+        let mut table = Self::new();
+        iter.into_iter().for_each(|toa| table.push(toa));
+        table
+    }
+}
+impl From<Vec<TOAInfo>> for ToaTable {
+    fn from(toas: Vec<TOAInfo>) -> Self {
+        toas.into_iter().collect()
+    }
+}
+impl From<&ToaTable> for Vec<TOAInfo> {
+    fn from(table: &ToaTable) -> Self {
+        table.to_toas()
+    }
+}
+
+/// Finds the value for an index among those that are sorted by index.
+fn sparse<T>(values: &[(usize, T)], index: usize) -> Option<&T> {
+    let position = values.binary_search_by_key(&index, |(i, _)| *i).ok()?;
+    Some(&values[position].1)
+}
+
+/// Keeps a single copy of each string, handing out ids for them. The copy
+/// is shared between the lookups both ways.
+#[derive(Debug, Clone, Default)]
+struct Interner {
+    strings: Vec<Arc<str>>,
+    ids: HashMap<Arc<str>, u32>,
+}
+impl Interner {
+    fn intern(&mut self, text: &str) -> u32 {
+        if let Some(id) = self.ids.get(text) {
+            return *id;
+        }
+
+        let id = u32::try_from(self.strings.len()).expect("Too many strings");
+        let text = Arc::<str>::from(text);
+        self.strings.push(Arc::clone(&text));
+        self.ids.insert(text, id);
+        id
+    }
+
+    fn find(&self, text: &str) -> Option<u32> {
+        self.ids.get(text).copied()
+    }
+
+    fn get(&self, id: u32) -> &str {
+        &self.strings[id as usize]
+    }
+}
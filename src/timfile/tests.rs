@@ -1,6 +1,15 @@
+#![allow(clippy::float_cmp)]
 #[allow(unused)]
 use super::*;
 #[allow(unused)]
+use crate::data_types::Mjd;
+#[allow(unused)]
+use crate::error::PsruError;
+#[cfg(test)]
+use crate::test_dir::TestDir;
+#[allow(unused)]
+use crate::warning::WarningKind;
+#[allow(unused)]
 use std::io::{BufReader, LineWriter};
 
 #[allow(unused)]
@@ -33,6 +42,23 @@ fn t2_bad() {
     TOAInfo::parse_tempo2(&[&["C"], T2_MINIMAL].concat()).unwrap();
 }
 #[test]
+fn empty_lines() {
+    for line in ["", "   ", "\t"] {
+        for format in [
+            TimFormat::Tempo2,
+            TimFormat::Parkes,
+            TimFormat::Princeton,
+            TimFormat::Itoa,
+            TimFormat::Auto,
+        ] {
+            assert!(
+                TOAInfo::from_line(line, format).is_err(),
+                "{format:?} '{line}'"
+            );
+        }
+    }
+}
+#[test]
 fn t2_comments() {
     let parts = [
         "#hii",
@@ -49,3 +75,754 @@ fn t2_comments() {
 // fn file() {
 //     read_tim("testing/test.tim".into(), TimFormat::Tempo2).unwrap();
 // }
+
+#[test]
+fn warnings() {
+    let dir = TestDir::new("tim-warnings");
+    let path = dir.join("main.tim");
+    std::fs::write(
+        &path,
+        "FORMAT 1\n\
+        MODE 1\n\
+        file 1400.0 55000.5 1.0 ao -empty -be PUPPI -dt -0.5\n\
+        file 1400.0 55001.5 0.0 ao\n",
+    )
+    .unwrap();
+
+    let (toas, warnings) =
+        read_tim_with_warnings(&path, TimFormat::Tempo2).unwrap();
+
+    assert_eq!(toas.len(), 2);
+    assert_eq!(toas[0].flags["empty"], Flag::String(String::new()));
+    assert_eq!(toas[0].flags["be"], Flag::new("PUPPI"));
+    assert_eq!(toas[0].flags["dt"], Flag::new("-0.5"));
+
+    let lines = warnings.iter().map(|w| w.line).collect::<Vec<_>>();
+    assert_eq!(lines, [2, 3, 4]);
+    assert_eq!(
+        warnings[0].kind,
+        WarningKind::UnsupportedDirective(String::from("MODE"))
+    );
+    assert_eq!(
+        warnings[1].kind,
+        WarningKind::EmptyFlag(String::from("empty"))
+    );
+}
+
+#[allow(unused)]
+const PARKES_LINE: &str = " J0437-4715               1400.000  \
+    55000.1234567890123    0.00    1.50        7";
+
+#[test]
+fn parkes() {
+    let toa = TOAInfo::parse_parkes(PARKES_LINE).unwrap();
+    assert!(!toa.is_bad);
+    assert_eq!(toa.file, "J0437-4715");
+    assert_eq!(toa.frequency, 1400.0);
+    assert_eq!(toa.mjd, "55000.1234567890123".parse().unwrap());
+    assert_eq!(toa.mjd_error, 1.5);
+    assert_eq!(toa.site_id, "7");
+    assert!(toa.flags.is_empty());
+
+    let bad = format!("C{}", &PARKES_LINE[1..]);
+    assert!(TOAInfo::parse_parkes(&bad).unwrap().is_bad);
+
+    let no_blank = format!("X{}", &PARKES_LINE[1..]);
+    assert!(matches!(
+        TOAInfo::parse_parkes(&no_blank),
+        Err(PsruError::TimParkesMissingBlank(_))
+    ));
+    assert!(matches!(
+        TOAInfo::parse_parkes(&PARKES_LINE[..79]),
+        Err(PsruError::TimUnexpectedEOL(_))
+    ));
+    let shifted = format!(" {PARKES_LINE}");
+    assert!(TOAInfo::parse_parkes(&shifted[..80]).is_err());
+
+    let bad_freq = PARKES_LINE.replace("1400.000", "14OO.000");
+    assert!(matches!(
+        TOAInfo::parse_parkes(&bad_freq),
+        Err(PsruError::TimBadColumn(_, "frequency", _))
+    ));
+}
+
+#[test]
+fn parkes_file() {
+    let dir = TestDir::new("tim-parkes");
+    let path = dir.join("legacy.tim");
+    let bad_mjd = PARKES_LINE.replace("55000.", "5500x.");
+    std::fs::write(&path, format!("{PARKES_LINE}\n\n{bad_mjd}\n")).unwrap();
+
+    let err = read_tim(&path, TimFormat::Parkes).unwrap_err();
+
+    assert!(matches!(err, PsruError::TimMalformedMJD(Some(_))));
+    assert!(err.to_string().contains("on line 3"));
+}
+
+#[allow(unused)]
+const PRINCETON_LINE: &str = "1 J1909-3744    1400.000 55000.1234567890123    0.500                   0.0012";
+
+#[allow(unused)]
+const ITOA_LINE: &str =
+    "1909-374455000.1234567890123  0.50   1400.000    0.0012  AO";
+
+#[test]
+fn princeton() {
+    let toa = TOAInfo::from_line(PRINCETON_LINE, TimFormat::Princeton).unwrap();
+    assert!(!toa.is_bad);
+    assert_eq!(toa.site_id, "1");
+    assert_eq!(toa.file, "J1909-3744");
+    assert_eq!(toa.frequency, 1400.0);
+    assert_eq!(toa.mjd, "55000.1234567890123".parse().unwrap());
+    assert_eq!(toa.mjd_error, 0.5);
+    assert_eq!(toa.flag_f64("ddm"), Some(0.0012));
+
+    let bad = format!("C1{}", &PRINCETON_LINE[2..]);
+    let bad = TOAInfo::parse_princeton(&bad).unwrap();
+    assert!(bad.is_bad);
+    assert_eq!(bad.site_id, "1");
+
+    let short = TOAInfo::parse_princeton(&PRINCETON_LINE[..53]).unwrap();
+    assert!(short.flags.is_empty());
+
+    let no_blank = format!("1X{}", &PRINCETON_LINE[2..]);
+    assert!(matches!(
+        TOAInfo::parse_princeton(&no_blank),
+        Err(PsruError::TimBadColumn(_, "blank", _))
+    ));
+    let shifted = format!("{}{}", &PRINCETON_LINE[..24], &PRINCETON_LINE[27..]);
+    assert!(matches!(
+        TOAInfo::parse_princeton(&shifted),
+        Err(PsruError::TimMalformedMJD(_))
+    ));
+}
+
+#[test]
+fn itoa() {
+    let toa = TOAInfo::from_line(ITOA_LINE, TimFormat::Itoa).unwrap();
+    assert!(!toa.is_bad);
+    assert_eq!(toa.site_id, "AO");
+    assert_eq!(toa.file, "1909-3744");
+    assert_eq!(toa.frequency, 1400.0);
+    assert_eq!(toa.mjd, "55000.1234567890123".parse().unwrap());
+    assert_eq!(toa.mjd_error, 0.5);
+    assert_eq!(toa.flag_f64("ddm"), Some(0.0012));
+
+    let bad = format!("C {}", &ITOA_LINE[2..]);
+    let bad = TOAInfo::parse_itoa(&bad).unwrap();
+    assert!(bad.is_bad);
+    assert_eq!(bad.file, "09-3744");
+
+    assert!(matches!(
+        TOAInfo::parse_itoa(&ITOA_LINE[..58]),
+        Err(PsruError::TimUnexpectedEOL(_))
+    ));
+    let no_site = format!("{}  ", &ITOA_LINE[..57]);
+    assert!(matches!(
+        TOAInfo::parse_itoa(&no_site),
+        Err(PsruError::TimBadColumn(_, "observatory", _))
+    ));
+}
+
+#[test]
+fn detect_format() {
+    assert_eq!(TimFormat::detect(PARKES_LINE), TimFormat::Parkes);
+    assert_eq!(TimFormat::detect(PRINCETON_LINE), TimFormat::Princeton);
+    assert_eq!(TimFormat::detect(ITOA_LINE), TimFormat::Itoa);
+    assert_eq!(
+        TimFormat::detect("fname 1400.0 55000.5 1.5 pks"),
+        TimFormat::Tempo2
+    );
+    assert_eq!(
+        TimFormat::detect(&format!("{PARKES_LINE} -fe L-wide")),
+        TimFormat::Tempo2
+    );
+
+    let toa = TOAInfo::from_line(ITOA_LINE, TimFormat::Auto).unwrap();
+    assert_eq!(toa.format, TimFormat::Itoa);
+}
+
+#[test]
+fn auto_format_includes() {
+    let dir = TestDir::new("tim-auto");
+    std::fs::write(dir.join("parkes.tim"), format!("{PARKES_LINE}\n")).unwrap();
+    std::fs::write(
+        dir.join("princeton.tim"),
+        format!("{PRINCETON_LINE}\n{PRINCETON_LINE}\n"),
+    )
+    .unwrap();
+    let master = "\
+FORMAT 1
+fname 1400.0 55000.5 1.5 pks
+INCLUDE parkes.tim
+INCLUDE princeton.tim
+fname 1400.0 55001.5 1.5 pks
+";
+    std::fs::write(dir.join("master.tim"), master).unwrap();
+
+    let auto = read_tim(&dir.join("master.tim"), TimFormat::Auto);
+    let strict = read_tim(&dir.join("master.tim"), TimFormat::Tempo2);
+
+    let formats = auto.unwrap().iter().map(|t| t.format).collect::<Vec<_>>();
+    assert_eq!(
+        formats,
+        [
+            TimFormat::Tempo2,
+            TimFormat::Parkes,
+            TimFormat::Princeton,
+            TimFormat::Princeton,
+            TimFormat::Tempo2,
+        ]
+    );
+    assert!(strict.is_err());
+}
+
+#[test]
+fn write_tempo2() {
+    let lines = [
+        "fname 1400.25 55000.123456789012345678 0.5 pks -fe L-wide -be PDFB",
+        "C fname 1400.25 55001.000000000000001 0.5 pks -to 0.4 # was bad",
+        "fname 1400.25 55002 0.5 pks -padd 1e-20 -empty",
+    ];
+    let toas = lines
+        .iter()
+        .map(|l| TOAInfo::from_line_tempo2(l).unwrap())
+        .collect::<Vec<_>>();
+
+    let mut dst = Vec::new();
+    write_tim(&mut dst, &toas, TimFormat::Tempo2).unwrap();
+    let text = String::from_utf8(dst).unwrap();
+
+    let expected = "\
+FORMAT 1
+fname 1400.25 55000.12345678901234568 0.5 pks -be PDFB -fe L-wide
+C fname 1400.25 55001.000000000000001 0.5 pks -to 0.4 # was bad
+fname 1400.25 55002.0 0.5 pks -padd 1e-20 -empty
+";
+    assert_eq!(text, expected);
+
+    let again = text
+        .lines()
+        .skip(1)
+        .map(|l| TOAInfo::from_line_tempo2(l).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(again, toas);
+    for (a, b) in again.iter().zip(&toas) {
+        assert_eq!(a.mjd.frac().to_bits(), b.mjd.frac().to_bits());
+    }
+}
+
+#[test]
+fn write_fixed_width() {
+    for (line, format) in [
+        (PARKES_LINE, TimFormat::Parkes),
+        (PRINCETON_LINE, TimFormat::Princeton),
+        (ITOA_LINE, TimFormat::Itoa),
+    ] {
+        let toas = vec![TOAInfo::from_line(line, format).unwrap()];
+        let mut dst = Vec::new();
+        write_tim(&mut dst, &toas, TimFormat::Auto).unwrap();
+        let text = String::from_utf8(dst).unwrap();
+
+        let again = TOAInfo::from_line(text.trim_end(), format).unwrap();
+        assert_eq!(again, toas[0]);
+        assert_eq!(TimFormat::detect(&text), format);
+    }
+
+    let toa = TOAInfo::from_line_tempo2("long-name 1400 55000 1 pks").unwrap();
+    assert!(matches!(
+        toa.to_line(TimFormat::Parkes),
+        Err(PsruError::TimBadColumn(_, "observatory", _))
+    ));
+    assert!(toa.to_line(TimFormat::Princeton).is_err());
+
+    let late = TOAInfo::from_line_tempo2("a 1400 100000.5 1 7").unwrap();
+    for format in [TimFormat::Princeton, TimFormat::Itoa] {
+        assert!(matches!(
+            late.to_line(format),
+            Err(PsruError::TimBadColumn(_, "MJD", _))
+        ));
+    }
+    let line = late.to_line(TimFormat::Parkes).unwrap();
+    assert_eq!(TOAInfo::from_line_parkes(&line).unwrap().mjd, late.mjd);
+}
+
+#[test]
+fn directives() {
+    let dir = TestDir::new("tim-directives");
+    let master = "\
+FORMAT 1
+# A comment
+TIME 0.5
+a 1400 55000.5 1.0 ao
+EFAC 2
+EQUAD 3
+a 1400 55001.5 2.0 ao
+JUMP
+PHASE 1
+a 1400 55002.5 1.0 ao -phase 0.5
+JUMP
+SKIP
+a 1400 55003.5 1.0 ao
+NOSKIP
+EMAX 4.5
+FMIN 1000
+a 1400 55004.5 5.0 ao
+a 900 55005.5 1.0 ao
+INCLUDE inc.tim
+a 1400 55006.5 1.0 ao
+";
+    std::fs::write(dir.join("master.tim"), master).unwrap();
+    std::fs::write(
+        dir.join("inc.tim"),
+        "TIME 0.25\nJUMP\nb 1400 55007.5 4 ao\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("bad.tim"), "EFAC two\n").unwrap();
+
+    let toas = read_tim(&dir.join("master.tim"), TimFormat::Tempo2).unwrap();
+    let bad = read_tim(&dir.join("bad.tim"), TimFormat::Tempo2);
+
+    let mut dst = Vec::new();
+    write_tim(&mut dst, &toas, TimFormat::Tempo2).unwrap();
+    std::fs::write(dir.join("written.tim"), &dst).unwrap();
+    let again = read_tim(&dir.join("written.tim"), TimFormat::Tempo2).unwrap();
+
+    let days = toas.iter().map(|t| t.mjd.int()).collect::<Vec<_>>();
+    assert_eq!(days, [55000, 55001, 55002, 55007, 55006]);
+    assert!((toas[0].mjd.frac() - (0.5 + 0.5 / 86400.0)).abs() < 1e-15);
+    assert!((toas[3].mjd.frac() - (0.5 + 0.75 / 86400.0)).abs() < 1e-15);
+
+    assert_eq!(toas[0].mjd_error, 1.0);
+    assert_eq!(toas[1].mjd_error, 5.0);
+    assert_eq!(toas[3].mjd_error, 8.0_f64.hypot(3.0));
+
+    assert_eq!(toas[2].flag_f64("phase"), Some(1.5));
+    assert_eq!(toas[2].flag_i64("tim_jump"), Some(1));
+    assert_eq!(toas[3].flag_i64("tim_jump"), Some(2));
+    assert!(!toas[4].flags.contains_key("tim_jump"));
+
+    assert_eq!(toas[0].directives, ["# A comment", "TIME 0.5"]);
+    assert_eq!(toas[3].directives, ["TIME 0.25", "JUMP"]);
+    assert!(matches!(bad, Err(PsruError::TimBadDirective(Some(_), _))));
+
+    assert_eq!(again.len(), toas.len());
+    for (a, b) in again.iter().zip(&toas) {
+        assert_eq!(
+            (a.mjd, a.mjd_error, &a.flags),
+            (b.mjd, b.mjd_error, &b.flags)
+        );
+    }
+    assert_eq!(again[0].directives, ["# A comment", "# TIME 0.5"]);
+}
+
+#[test]
+fn directive_ranges() {
+    let reader = TimReader::new(TimFormat::Tempo2);
+
+    let tim = "TIME -100000\na 1400 0.0001 1.0 ao\n";
+    let err = reader.read(tim.as_bytes(), "early.tim").unwrap_err();
+    assert!(
+        matches!(err, PsruError::TimDirectiveOutOfRange(Some(_), _)),
+        "{err}"
+    );
+    assert!(err.to_string().contains("'early.tim' on line 2"), "{err}");
+
+    let tim = "TIME 1e30\na 1400 55000.5 1.0 ao\n";
+    let err = reader.read(tim.as_bytes(), "late.tim").unwrap_err();
+    assert!(
+        matches!(err, PsruError::TimDirectiveOutOfRange(Some(_), _)),
+        "{err}"
+    );
+
+    for directive in ["TIME inf", "PHASE NaN", "EFAC inf", "EQUAD -inf"] {
+        let tim = format!("{directive}\na 1400 55000.5 1.0 ao\n");
+        let err = reader.read(tim.as_bytes(), "bad.tim").unwrap_err();
+        assert!(
+            matches!(err, PsruError::TimBadDirective(Some(_), _)),
+            "{err}"
+        );
+        assert!(err.to_string().contains("'bad.tim' on line 1"), "{err}");
+    }
+
+    let tim = "TIME 1e308\nTIME 1e308\na 1400 55000.5 1.0 ao\n";
+    let err = reader.read(tim.as_bytes(), "sum.tim").unwrap_err();
+    assert!(
+        matches!(err, PsruError::TimBadDirective(Some(_), _)),
+        "{err}"
+    );
+
+    let tim = "TIME -0.5\na 1400 0.0001 1.0 ao\n";
+    let (toas, _) = reader.read(tim.as_bytes(), "fine.tim").unwrap();
+    assert_eq!(toas.len(), 1);
+}
+
+#[test]
+fn read_from_memory() {
+    let resolver = MemoryResolver::new()
+        .with_file("sub/a.tim", "TIME 1\nINCLUDE b.tim\n")
+        .with_file("sub/b.tim", "b 1400 55001.5 1.0 ao\nb 1400 55001.6 1.0\n")
+        .with_file("d.tim", "d 1400 55002.5 1.0 ao\n");
+    let reader = TimReader::new(TimFormat::Tempo2).with_resolver(&resolver);
+
+    let main = "a 1400 55000.5 1.0 ao\nINCLUDE d.tim\nINCLUDE sub/a.tim\n";
+    let err = reader.read(main.as_bytes(), "main.tim").unwrap_err();
+    assert!(err.to_string().contains("'sub/b.tim' on line 2"), "{err}");
+
+    let main = "a 1400 55000.5 1.0 ao\nINCLUDE d.tim\n";
+    let (toas, _) = reader.read(main.as_bytes(), "main.tim").unwrap();
+    assert_eq!(toas.len(), 2);
+    assert_eq!(toas[1].file, "d");
+
+    let main = "INCLUDE missing.tim\n";
+    let err = reader.read(main.as_bytes(), "main.tim").unwrap_err();
+    assert!(matches!(err, PsruError::IOError(_)));
+}
+
+#[test]
+fn include_cycles() {
+    let resolver = MemoryResolver::new()
+        .with_file("a.tim", "a 1400 55000.5 1.0 ao\nINCLUDE b.tim\n")
+        .with_file("b.tim", "INCLUDE c.tim\n")
+        .with_file("c.tim", "INCLUDE a.tim\n")
+        .with_file("d.tim", "d 1400 55001.5 1.0 ao\n")
+        .with_file("e.tim", "INCLUDE d.tim\n");
+    let reader = TimReader::new(TimFormat::Tempo2).with_resolver(&resolver);
+
+    let err = reader.read(&b"INCLUDE a.tim\n"[..], "main.tim");
+    let Err(PsruError::TimIncludeCycle(Some(ctx), chain)) = err else {
+        panic!("Expected a cycle, got {err:?}");
+    };
+    assert_eq!(chain, ["main.tim", "a.tim", "b.tim", "c.tim", "a.tim"]);
+    assert!(ctx.to_string().contains("'c.tim' on line 1"));
+
+    let main = "INCLUDE d.tim\nINCLUDE e.tim\n";
+    let (toas, warnings) = reader.read(main.as_bytes(), "main.tim").unwrap();
+    assert_eq!(toas.len(), 2);
+    assert!(warnings.is_empty());
+
+    let (toas, warnings) = reader
+        .with_dedup(true)
+        .read(main.as_bytes(), "main.tim")
+        .unwrap();
+    assert_eq!(toas.len(), 1);
+    assert_eq!(
+        warnings[0].kind,
+        WarningKind::RepeatedInclude(String::from("d.tim"))
+    );
+
+    let err = reader
+        .with_max_depth(1)
+        .read(&b"INCLUDE e.tim\n"[..], "main.tim");
+    assert!(matches!(
+        err,
+        Err(PsruError::TimIncludeTooDeep(_, chain)) if chain.len() == 3
+    ));
+}
+
+#[test]
+fn include_aliases() {
+    let resolver = MemoryResolver::new()
+        .with_file("a.tim", "INCLUDE ./sub/../b.tim\n")
+        .with_file("./b.tim", "INCLUDE sub/./../a.tim\n")
+        .with_file("d.tim", "d 1400 55001.5 1.0 ao\n");
+    let reader = TimReader::new(TimFormat::Tempo2).with_resolver(&resolver);
+
+    let err = reader.read(&b"INCLUDE a.tim\n"[..], "main.tim");
+    let Err(PsruError::TimIncludeCycle(_, chain)) = err else {
+        panic!("Expected a cycle, got {err:?}");
+    };
+    assert_eq!(chain, ["main.tim", "a.tim", "b.tim", "a.tim"]);
+
+    let main = "INCLUDE d.tim\nINCLUDE ./d.tim\nINCLUDE x/../d.tim\n";
+    let (toas, warnings) = reader
+        .with_dedup(true)
+        .read(main.as_bytes(), "main.tim")
+        .unwrap();
+    assert_eq!(toas.len(), 1);
+    assert_eq!(warnings.len(), 2);
+
+    let dir = TestDir::new("tim-aliases");
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    std::fs::write(dir.join("a.tim"), "INCLUDE sub/b.tim\n").unwrap();
+    std::fs::write(dir.join("sub/b.tim"), "INCLUDE ../a.tim\n").unwrap();
+    std::fs::write(
+        dir.join("d.tim"),
+        "INCLUDE sub/c.tim\nINCLUDE ./sub/../sub/c.tim\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("sub/c.tim"), "x 1400 55001.5 1.0 ao\n").unwrap();
+
+    let err = read_tim(&dir.join("./sub/../a.tim"), TimFormat::Tempo2);
+    let Err(PsruError::TimIncludeCycle(_, chain)) = err else {
+        panic!("Expected a cycle, got {err:?}");
+    };
+    assert_eq!(chain.len(), 3);
+    assert_eq!(chain[0], chain[2]);
+
+    let (toas, warnings) = TimReader::new(TimFormat::Tempo2)
+        .with_dedup(true)
+        .read_path(&dir.join("d.tim"))
+        .unwrap();
+    assert_eq!(toas.len(), 1);
+    assert!(matches!(warnings[0].kind, WarningKind::RepeatedInclude(_)));
+}
+
+#[test]
+fn lazy_toas() {
+    let opened = std::cell::Cell::new(0);
+    let resolver = |_: &str, target: &str| -> Result<TimSource, PsruError> {
+        opened.set(opened.get() + 1);
+        let contents = "x 1400 55000.5 1.0 ao\nx 1400 55001.5 1.0 ao\n";
+        Ok((target.to_string(), Box::new(contents.as_bytes())))
+    };
+    let main = "INCLUDE a.tim\nINCLUDE b.tim\nm 1400 55002.5 1.0 ao\nbad\n";
+    let reader = TimReader::new(TimFormat::Tempo2).with_resolver(&resolver);
+
+    let first = reader.toas(main.as_bytes(), "main.tim").next();
+    assert!(first.unwrap().is_ok());
+    assert_eq!(opened.get(), 1);
+
+    let results = reader.toas(main.as_bytes(), "main.tim").collect::<Vec<_>>();
+    assert_eq!(results.len(), 6);
+    assert!(results[..5].iter().all(Result::is_ok));
+    assert!(matches!(
+        &results[5],
+        Err(PsruError::TimUnexpectedEOL(Some(ctx))) if ctx.to_string().contains("line 4")
+    ));
+
+    let dir = TestDir::new("tim-lazy");
+    std::fs::write(dir.join("a.tim"), "a 1400 55000.5 1.0 ao\n").unwrap();
+    let count = iter_tim(&dir.join("a.tim"), TimFormat::Tempo2)
+        .unwrap()
+        .filter(|toa| toa.as_ref().is_ok_and(|t| t.frequency > 1000.0))
+        .count();
+    assert_eq!(count, 1);
+}
+
+#[test]
+fn toa_table() {
+    let dir = TestDir::new("tim-table");
+    let text = "\
+FORMAT 1
+a 1400 55000.5 1.0 ao -fe L-wide -pn 1 # first
+EFAC 2
+C a 430 55001.5 1.0 ao -fe 430
+b 1400 55002.5 1.0 gbt -be GUPPI
+";
+    std::fs::write(dir.join("a.tim"), text).unwrap();
+    let toas = read_tim(&dir.join("a.tim"), TimFormat::Tempo2).unwrap();
+    let table = read_tim_table(&dir.join("a.tim"), TimFormat::Tempo2).unwrap();
+
+    assert_eq!(table.to_toas(), toas);
+    assert_eq!(ToaTable::from(toas.clone()).to_toas(), toas);
+
+    assert_eq!(table.bad(), [false, true, false]);
+    assert_eq!(table.errors(), [1.0, 2.0, 2.0]);
+    assert_eq!(table.files().collect::<Vec<_>>(), ["a", "a", "b"]);
+    assert_eq!(table.comment(0), Some("first"));
+    assert_eq!(table.comment(1), None);
+    assert_eq!(table.with_flag("fe", "L-wide"), [0]);
+    assert_eq!(
+        table.flag_values("pn").unwrap().collect::<Vec<_>>(),
+        [Some(FlagRef::Int(1, "1")), None, None]
+    );
+    assert!(table.flag_values("nope").is_none());
+    assert_eq!(table.flag(1, "fe"), Some(FlagRef::Int(430, "430")));
+    assert_eq!(table.flag(2, "be"), Some(FlagRef::String("GUPPI")));
+    assert_eq!(table.flag(2, "fe"), None);
+    assert_eq!(table.flag(3, "fe"), None);
+    let mut keys = table.flag_keys().collect::<Vec<_>>();
+    keys.sort_unstable();
+    assert_eq!(keys, ["be", "fe", "pn"]);
+    assert!(table.get(3).is_none());
+}
+
+#[test]
+fn read_matches_lazy() {
+    use std::fmt::Write as _;
+    use std::io::Read as _;
+
+    // Gives an included file time to be parsed before reading on
+    struct Pause;
+    impl std::io::Read for Pause {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            Ok(0)
+        }
+    }
+
+    let mut main = String::from("FORMAT 1\nMODE 1\n");
+    for i in 0..40000 {
+        _ = writeln!(main, "a 1400 {}.5 1.0 ao -i {i}", 20000 + i);
+        if i % 7000 == 0 {
+            main += "INCLUDE inc.tim\nJUMP\n# note\nSKIP\n";
+            main += "a 1400 55000.5 1.0 ao\nNOSKIP\n";
+        }
+    }
+    let resolver = MemoryResolver::new()
+        .with_file("inc.tim", "TIME 1\nb 1400 55000.5 0.0 ao -empty\n");
+    let reader = TimReader::new(TimFormat::Auto).with_resolver(&resolver);
+
+    let (toas, warnings) = reader.read(main.as_bytes(), "main.tim").unwrap();
+    let mut lazy = reader.toas(main.as_bytes(), "main.tim");
+    let lazy_toas = lazy.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+
+    assert_eq!(toas.len(), 40006);
+    assert_eq!(toas, lazy_toas);
+    assert_eq!(warnings, lazy.take_warnings());
+    assert_eq!(toas[2].directives, ["JUMP", "# note", "SKIP", "NOSKIP"]);
+    drop(lazy);
+
+    main += "a 1400 x 1.0 ao\nEFAC x\n";
+    let middle = main[main.len() / 2..].find('\n').unwrap() + main.len() / 2;
+    main.insert_str(middle + 1, "b 1400 55000.5 1.0\n");
+    let err = reader.read(main.as_bytes(), "main.tim").unwrap_err();
+    let lazy_err = reader
+        .toas(main.as_bytes(), "main.tim")
+        .find_map(Result::err)
+        .unwrap();
+    assert!(matches!(err, PsruError::TimUnexpectedEOL(Some(_))));
+    assert_eq!(err.to_string(), lazy_err.to_string());
+
+    let resolver = MemoryResolver::new()
+        .with_file("bad.tim", "a 1400 55000.5 1.0 ao\nb 1400 55000.5 1.0\n");
+    let reader = TimReader::new(TimFormat::Auto).with_resolver(&resolver);
+    let mut rest = std::io::Cursor::new(main.as_bytes());
+    let source = b"INCLUDE bad.tim\n"
+        .as_slice()
+        .chain(Pause)
+        .chain(&mut rest);
+    let err = reader.read(BufReader::new(source), "main.tim").unwrap_err();
+    assert!(err.to_string().contains("bad.tim' on line 2"), "{err}");
+    assert!(rest.position() < main.len() as u64 / 2);
+}
+
+#[test]
+fn flag_text() {
+    let line = "a 1400 55000.5 1.0 ao -chan 007 -be 1e3 -pn 9007199254740993 \
+        -snr 12.50 -fe L-wide";
+    let toa = TOAInfo::from_line_tempo2(line).unwrap();
+
+    assert_eq!(toa.flags["chan"], Flag::Int(7, String::from("007")));
+    assert_eq!(toa.flag_str("chan"), Some("007"));
+    assert_eq!(toa.flag_str("be"), Some("1e3"));
+    assert_eq!(toa.flag_f64("be"), Some(1000.0));
+    assert_eq!(toa.flag_i64("be"), None);
+    assert_eq!(toa.flag_i64("pn"), Some(9_007_199_254_740_993));
+    assert_eq!(toa.flag_f64("snr"), Some(12.5));
+    assert_eq!(toa.flag_f64("fe"), None);
+    assert_eq!(toa.flag_str("fe"), Some("L-wide"));
+    assert_eq!(toa.flag_str("nope"), None);
+
+    let written = toa.to_line(TimFormat::Tempo2).unwrap();
+    assert_eq!(
+        written,
+        "a 1400 55000.5 1 ao -be 1e3 -chan 007 -fe L-wide \
+        -pn 9007199254740993 -snr 12.50"
+    );
+    assert_eq!(TOAInfo::from_line_tempo2(&written).unwrap(), toa);
+
+    let table = ToaTable::from(vec![toa.clone()]);
+    assert_eq!(table.flag(0, "chan").map(|f| f.text()), Some("007"));
+    assert_eq!(table.with_flag("snr", "12.50"), [0]);
+    assert_eq!(table.to_toas(), [toa]);
+}
+
+#[test]
+fn selector() {
+    let toas = [
+        "a 1400 56500.5 1.0 ao -be PUPPI -fe L-wide",
+        "C a 1400 56501.5 1.0 ao -be PUPPI -fe L-wide",
+        "b 430 56502.5 3.0 ao -be PUPPI -fe 430",
+        "b 1400 55000.5 1.0 gbt -be GUPPI",
+    ]
+    .map(|l| TOAInfo::from_line_tempo2(l).unwrap());
+
+    let indices = |text: &str| text.parse::<Selector>().unwrap().indices(&toas);
+    assert_eq!(indices(""), [0, 1, 2, 3]);
+    assert_eq!(indices("-be PUPPI FREQ 1300 1500 MJD 56000 * NOT BAD"), [0]);
+    assert_eq!(indices("pass mjd * 56501.5 reject tel gbt"), [0, 1]);
+    assert_eq!(indices("ERR 2 * NAME b"), [2]);
+    assert_eq!(indices("NOT -fe L-wide"), [2, 3]);
+    assert_eq!(indices("NOT NOT BAD"), [1]);
+
+    assert!(matches!(
+        "FREQ x 2".parse::<Selector>(),
+        Err(PsruError::Unparsable { .. })
+    ));
+    for bad in ["MJD 1", "NOT", "FOO", "- x", "-fe"] {
+        assert!(
+            matches!(bad.parse::<Selector>(), Err(PsruError::BadSelector(_))),
+            "'{bad}' should fail"
+        );
+    }
+
+    let selector = Selector::new()
+        .with(Condition::Mjd(56000.0, f64::INFINITY))
+        .without(Condition::Bad)
+        .with(Condition::Flag(String::from("be"), String::from("PUPPI")));
+    assert_eq!(selector.to_string(), "MJD 56000 * NOT BAD -be PUPPI");
+    assert_eq!(selector.to_string().parse::<Selector>().unwrap(), selector);
+    assert_eq!(selector.filter(&toas).count(), 2);
+}
+
+#[test]
+fn summary() {
+    let toas = [
+        "a 1400 55000.25 1.0 ao -be PUPPI -fe L-wide",
+        "a 1400 55000.5 4.0 ao -be PUPPI -fe L-wide",
+        "a 430 55010.5 2.0 ao -be PUPPI -fe 430",
+        "C a 1400 55011.5 1.0 gbt -be GUPPI -fe L-wide",
+        "a 800 55030.5 0.0 gbt -be GUPPI",
+        "a 1400 55050.5 2.0 gbt -be GUPPI -fe L-wide",
+    ]
+    .map(|l| TOAInfo::from_line_tempo2(l).unwrap());
+
+    let summary = Summary::new(&toas);
+    assert_eq!(summary.count, 6);
+    assert_eq!(summary.bad, 1);
+    assert_eq!(
+        summary.span,
+        Some((Mjd::new(55000, 0.25), Mjd::new(55050, 0.5)))
+    );
+    assert_eq!(summary.frequencies, Some((430.0, 1400.0)));
+    assert_eq!(summary.median_error, Some(2.0));
+    assert_eq!(summary.weighted_error, Some(0.8));
+    assert_eq!(summary.days, 4);
+    assert_eq!(summary.cadence, Some(20.0));
+    assert_eq!(summary.gaps.len(), 4);
+    assert_eq!(summary.gaps[0].0, Mjd::new(55010, 0.5));
+    assert_eq!(summary.gaps[3].1, Mjd::new(55000, 0.5));
+    assert_eq!(
+        summary.sites,
+        [(String::from("ao"), 3), (String::from("gbt"), 2)]
+    );
+    assert_eq!(
+        summary.backends,
+        [(String::from("GUPPI"), 2), (String::from("PUPPI"), 3)]
+    );
+    assert!(summary.groups.is_empty());
+
+    let grouped = Summary::by_flag(&toas, "fe");
+    assert_eq!(grouped.count, summary.count);
+    let values = grouped
+        .groups
+        .iter()
+        .map(|(value, group)| (value.as_deref(), group.count, group.bad))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        values,
+        [(Some("430"), 1, 0), (Some("L-wide"), 4, 1), (None, 1, 0)]
+    );
+
+    let text = grouped.to_string();
+    assert!(text.starts_with("TOAs            6 (1 bad)\n"));
+    assert!(text.contains("\nSites           ao: 3, gbt: 2\n"));
+    assert!(text.contains("\n(none)      1     0   55030.500"));
+    assert!(text.ends_with("     800.0     800.0          -\n"));
+
+    let empty = Summary::new(&[]);
+    assert_eq!((empty.count, empty.span, empty.cadence), (0, None, None));
+    assert!(empty.to_string().contains("Sites           -"));
+}
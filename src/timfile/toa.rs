@@ -1,10 +1,13 @@
 use std::collections::HashMap;
+use std::ops::Range;
 
+use super::TimFormat;
 use crate::data_types::Mjd;
 use crate::error::PsruError;
 use crate::parse_tools::parse_f64;
+use crate::warning::WarningKind;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 /// The basic information contained in a calculated TOA.
 pub struct TOAInfo {
     /// Whether the TOA is marked as bad.
@@ -25,18 +28,41 @@ pub struct TOAInfo {
     /// Any comments left in the line.
     pub comment: String,
 
-    /// All flags found. Which flags are used depends on the file's creator,
-    /// but they are all put as either of two versions: `f64` and `String`.
+    /// All flags found. Which flags are used depends on the file's creator.
+    /// Each keeps its text, and is read as an integer, a double, or a
+    /// string; see [`Flag`].
     pub flags: HashMap<String, Flag>,
+
+    /// The format of the line the TOA was read from.
+    pub format: TimFormat,
+    /// The directive and comment lines found between the previous TOA of
+    /// the same file and this one, as they were written. Their effects have
+    /// already been applied.
+    pub directives: Vec<String>,
 }
 impl TOAInfo {
+    /// The text of a flag, as written, if the TOA has it.
+    pub fn flag_str(&self, key: &str) -> Option<&str> {
+        self.flags.get(key).map(Flag::text)
+    }
+
+    /// The value of a flag, if the TOA has it and it is an integer.
+    pub fn flag_i64(&self, key: &str) -> Option<i64> {
+        self.flags.get(key)?.as_i64()
+    }
+
+    /// The value of a flag, if the TOA has it and it is a number.
+    pub fn flag_f64(&self, key: &str) -> Option<f64> {
+        self.flags.get(key)?.as_f64()
+    }
+
     /// Parses a single line of .tim-file information in TEMPO2-style.
     ///
     /// # Errors
     /// Returns errors for any malformed parameters.
     ///
     /// ```
-    /// # use psrutils::timfile::{TOAInfo, Flag};
+    /// # use psrutils::timfile::{TOAInfo, Flag, TimFormat};
     /// # use psrutils::data_types::Mjd;
     /// # use std::collections::HashMap;
     /// let line = "fname 1.0 55.0 0.0 st -flag value -flag2 42";
@@ -49,10 +75,12 @@ impl TOAInfo {
     ///     mjd_error: 0.0,
     ///     site_id: String::from("st"),
     ///     flags: HashMap::from([
-    ///         (String::from("flag"), Flag::String(String::from("value"))),
-    ///         (String::from("flag2"), Flag::Double(42.0)),
+    ///         (String::from("flag"), Flag::new("value")),
+    ///         (String::from("flag2"), Flag::Int(42, String::from("42"))),
     ///     ]),
     ///     comment: String::new(),
+    ///     format: TimFormat::Tempo2,
+    ///     directives: Vec::new(),
     /// };
     /// assert_eq!(info, toa);
     /// ```
@@ -64,16 +92,20 @@ impl TOAInfo {
     /// Reads in tempo2 format. Comments are a little more allwoing than should
     /// be...
     pub(crate) fn parse_tempo2(parts: &[&str]) -> Result<Self, PsruError> {
-        let is_bad = parts[0] == "c" || parts[0] == "C";
-        let (mut comments, mut values): (Vec<&str>, Vec<&str>) = parts
+        let first = parts.first().ok_or(PsruError::TimUnexpectedEOL(None))?;
+        let is_bad = *first == "c" || *first == "C";
+        let (comments, mut values): (Vec<&str>, Vec<&str>) = parts
             .iter()
             .partition(|w| w.starts_with('#') && w.len() > 1);
 
-        if let Some(pos) = values.iter().position(|w| *w == "#") {
-            values.split_off(pos)[1..]
-                .iter()
-                .for_each(|c| comments.push(c));
-        }
+        // Everything after a lone `#` is a single comment
+        let trailing = values
+            .iter()
+            .position(|w| *w == "#")
+            .map(|pos| values.split_off(pos)[1..].join(" "));
+        let mut comments =
+            comments.into_iter().map(str::to_string).collect::<Vec<_>>();
+        comments.extend(trailing);
 
         let comment = comments.join(" -- ");
         let mut values = values.into_iter();
@@ -104,20 +136,24 @@ impl TOAInfo {
             .ok_or(PsruError::TimUnexpectedEOL(None))?
             .to_string();
 
-        // Flags come in key-value pairs
-        let remains = values.collect::<Vec<_>>();
-        let chunks = remains.chunks_exact(2);
-        if !chunks.remainder().is_empty() {
-            return Err(PsruError::TimUnvaluedFlag(
-                None,
-                chunks.remainder()[0].to_string(),
-            ));
+        // Flags come in key-value pairs, but a flag may be empty, in which
+        // case it is followed directly by the next flag, or by nothing
+        let mut remains = values.peekable();
+        let mut flags = HashMap::new();
+        while let Some(key) = remains.next() {
+            let (key, flag) = match remains.next_if(|v| !is_flag_key(v)) {
+                Some(value) => parse_flag(key, value),
+                None if key.starts_with('-') => parse_empty_flag(key),
+                None => {
+                    return Err(PsruError::TimUnvaluedFlag(
+                        None,
+                        key.to_string(),
+                    ));
+                }
+            };
+            flags.insert(key, flag);
         }
 
-        let flags = chunks
-            .map(|s| parse_flag(s[0], s[1]))
-            .collect::<HashMap<String, Flag>>();
-
         Ok(Self {
             is_bad,
             file,
@@ -127,30 +163,298 @@ impl TOAInfo {
             site_id,
             comment,
             flags,
+            format: TimFormat::Tempo2,
+            directives: Vec::new(),
         })
     }
 
-    /// Not fully implemented.
+    /// Lists anything odd about the values, that is still not an error.
+    pub(crate) fn oddities(&self) -> Vec<WarningKind> {
+        let mut empty = self
+            .flags
+            .iter()
+            .filter(|(_, v)| v.is_empty())
+            .map(|(k, _)| k.clone())
+            .collect::<Vec<_>>();
+        empty.sort();
+
+        let mut oddities = empty
+            .into_iter()
+            .map(WarningKind::EmptyFlag)
+            .collect::<Vec<_>>();
+
+        if self.mjd_error <= 0.0 {
+            oddities.push(WarningKind::SuspiciousValue {
+                name: String::from("error"),
+                reason: "non-positive uncertainty",
+            });
+        }
+        if self.frequency < 0.0 {
+            oddities.push(WarningKind::SuspiciousValue {
+                name: String::from("frequency"),
+                reason: "negative frequency",
+            });
+        }
+
+        oddities
+    }
+
+    /// Parses a single line of .tim-file information in any format, where
+    /// [`TimFormat::Auto`] guesses it from the line. Note that lines with
+    /// directives (e.g. `INCLUDE`) are not TOAs, and will fail.
+    ///
+    /// # Errors
+    /// Returns errors for any malformed parameters or columns.
+    pub fn from_line(line: &str, format: TimFormat) -> Result<Self, PsruError> {
+        match format {
+            TimFormat::Tempo2 => Self::from_line_tempo2(line),
+            TimFormat::Parkes => Self::parse_parkes(line),
+            TimFormat::Princeton => Self::parse_princeton(line),
+            TimFormat::Itoa => Self::parse_itoa(line),
+            TimFormat::Auto => Self::from_line(line, TimFormat::detect(line)),
+        }
+    }
+
+    /// Parses a single line of .tim-file information in Parkes format.
+    ///
+    /// # Errors
+    /// Returns errors for any malformed columns.
+    ///
+    /// ```
+    /// # use psrutils::timfile::{TOAInfo, Flag};
+    /// # use psrutils::data_types::Mjd;
+    /// let line = " J0437-4715               1400.000  55000.5000000000000    0.10    1.50        7";
+    /// let info = TOAInfo::from_line_parkes(line).unwrap();
+    ///
+    /// assert_eq!(info.file, "J0437-4715");
+    /// assert_eq!(info.frequency, 1400.0);
+    /// assert_eq!(info.mjd, Mjd::new(55000, 0.5));
+    /// assert_eq!(info.mjd_error, 1.5);
+    /// assert_eq!(info.site_id, "7");
+    /// assert_eq!(info.flag_f64("phase"), Some(0.1));
+    /// ```
+    pub fn from_line_parkes(line: &str) -> Result<Self, PsruError> {
+        Self::parse_parkes(line)
+    }
+
+    /// Reads in Parkes format, which is fixed-width:
+    ///
+    /// | Columns | Contents                                       |
+    /// |---------|------------------------------------------------|
+    /// | 1       | Blank, or `C` if the TOA is commented out      |
+    /// | 2-25    | Name, often of the file or pulsar (optional)   |
+    /// | 26-34   | Observing frequency (MHz)                      |
+    /// | 35-55   | TOA (MJD), with the decimal point in column 42 |
+    /// | 56-63   | Phase offset, in pulse periods (optional)      |
+    /// | 64-71   | TOA uncertainty (µs)                           |
+    /// | 80      | Observatory code                               |
+    ///
+    /// A non-zero phase offset is kept as the flag `phase`.
     pub(crate) fn parse_parkes(line: &str) -> Result<Self, PsruError> {
         if !line.is_ascii() {
             return Err(PsruError::TimNotAscii(None));
         }
 
-        if &line[0..1] != " " {
+        let is_bad = matches!(line.as_bytes().first(), Some(b'C' | b'c'));
+        if !is_bad && !line.starts_with(' ') {
             return Err(PsruError::TimParkesMissingBlank(None));
         }
+        if line.len() < 80 {
+            return Err(PsruError::TimUnexpectedEOL(None));
+        }
         if &line[41..42] != "." {
             return Err(PsruError::TimParkesMissingPeriod(None));
         }
-        // let freq_text = &line[25..34];
-        // let toa_int = &line[34..41];
-        // let toa_frac = &line[42..54];
-        // let phase = &line[55..62];
-        // let toa_err = &line[63..70];
-        // let observatory = &line[79..80];
 
-        todo!()
+        let file = column(line, 1..25).to_string();
+        let frequency = parse_column(line, 25..34, "frequency")?;
+
+        let mjd = parse_mjd(&format!(
+            "{}.{}",
+            column(line, 34..41),
+            column(line, 42..55)
+        ))?;
+
+        let mut flags = HashMap::new();
+        if !column(line, 55..63).is_empty() {
+            let phase = parse_column(line, 55..63, "phase offset")?;
+            if phase != 0.0 {
+                let phase = Flag::new(phase.to_string());
+                flags.insert(String::from("phase"), phase);
+            }
+        }
+
+        let mjd_error = parse_column(line, 63..71, "uncertainty")?;
+
+        let site_id = column(line, 79..80).to_string();
+        if site_id.is_empty() {
+            return Err(PsruError::TimBadColumn(None, "observatory", site_id));
+        }
+
+        Ok(Self {
+            is_bad,
+            file,
+            frequency,
+            mjd,
+            mjd_error,
+            site_id,
+            comment: String::new(),
+            flags,
+            format: TimFormat::Parkes,
+            directives: Vec::new(),
+        })
     }
+
+    /// Reads in Princeton format, which is fixed-width:
+    ///
+    /// | Columns | Contents                                               |
+    /// |---------|--------------------------------------------------------|
+    /// | 1       | Observatory code, or `C` if the TOA is commented out   |
+    /// | 2       | Blank, or the observatory code for commented out TOAs  |
+    /// | 3-15    | Name, often of the file or pulsar (optional)           |
+    /// | 16-24   | Observing frequency (MHz)                              |
+    /// | 25-44   | TOA (MJD), with the decimal point in column 30 or 31   |
+    /// | 45-53   | TOA uncertainty (µs)                                   |
+    /// | 69-78   | DM correction (cm^-3 pc, optional)                     |
+    ///
+    /// A DM correction is kept as the flag `ddm`.
+    pub(crate) fn parse_princeton(line: &str) -> Result<Self, PsruError> {
+        if !line.is_ascii() {
+            return Err(PsruError::TimNotAscii(None));
+        }
+        if line.len() < 53 {
+            return Err(PsruError::TimUnexpectedEOL(None));
+        }
+
+        // Commented out TOAs have their observatory code shifted one step
+        let is_bad = line.starts_with(['C', 'c']) && &line[1..2] != " ";
+        let site_id = if is_bad { &line[1..2] } else { &line[0..1] };
+        if site_id.trim().is_empty() {
+            return Err(PsruError::TimBadColumn(
+                None,
+                "observatory",
+                site_id.to_string(),
+            ));
+        }
+        if !is_bad && &line[1..2] != " " {
+            return Err(PsruError::TimBadColumn(
+                None,
+                "blank",
+                line[1..2].to_string(),
+            ));
+        }
+
+        if !matches!(line[24..44].find('.'), Some(5 | 6)) {
+            return Err(PsruError::TimMalformedMJD(None));
+        }
+
+        let mut flags = HashMap::new();
+        if !column(line, 68..78).is_empty() {
+            let ddm = parse_column(line, 68..78, "DM correction")?;
+            flags.insert(String::from("ddm"), Flag::new(ddm.to_string()));
+        }
+
+        Ok(Self {
+            is_bad,
+            file: column(line, 2..15).to_string(),
+            frequency: parse_column(line, 15..24, "frequency")?,
+            mjd: parse_mjd(column(line, 24..44))?,
+            mjd_error: parse_column(line, 44..53, "uncertainty")?,
+            site_id: site_id.to_string(),
+            comment: String::new(),
+            flags,
+            format: TimFormat::Princeton,
+            directives: Vec::new(),
+        })
+    }
+
+    /// Reads in ITOA format, which is fixed-width:
+    ///
+    /// | Columns | Contents                                       |
+    /// |---------|------------------------------------------------|
+    /// | 1-9     | Name, often of the pulsar (optional)           |
+    /// | 10-28   | TOA (MJD), with the decimal point in column 15 |
+    /// | 29-34   | TOA uncertainty (µs)                           |
+    /// | 35-45   | Observing frequency (MHz)                      |
+    /// | 46-55   | DM correction (cm^-3 pc, optional)             |
+    /// | 58-59   | Observatory code (two letters)                 |
+    ///
+    /// A line starting with `C ` is a commented out TOA. A DM correction is
+    /// kept as the flag `ddm`.
+    pub(crate) fn parse_itoa(line: &str) -> Result<Self, PsruError> {
+        if !line.is_ascii() {
+            return Err(PsruError::TimNotAscii(None));
+        }
+        if line.len() < 59 {
+            return Err(PsruError::TimUnexpectedEOL(None));
+        }
+        if &line[14..15] != "." {
+            return Err(PsruError::TimMalformedMJD(None));
+        }
+
+        let is_bad = line.starts_with("C ") || line.starts_with("c ");
+        let name = column(line, 0..9);
+        let name = if is_bad { name[1..].trim_start() } else { name };
+
+        let site_id = column(line, 57..59).to_string();
+        if site_id.is_empty() {
+            return Err(PsruError::TimBadColumn(None, "observatory", site_id));
+        }
+
+        let mut flags = HashMap::new();
+        if !column(line, 45..55).is_empty() {
+            let ddm = parse_column(line, 45..55, "DM correction")?;
+            flags.insert(String::from("ddm"), Flag::new(ddm.to_string()));
+        }
+
+        Ok(Self {
+            is_bad,
+            file: name.to_string(),
+            frequency: parse_column(line, 34..45, "frequency")?,
+            mjd: parse_mjd(column(line, 9..28))?,
+            mjd_error: parse_column(line, 28..34, "uncertainty")?,
+            site_id,
+            comment: String::new(),
+            flags,
+            format: TimFormat::Itoa,
+            directives: Vec::new(),
+        })
+    }
+}
+
+/// The trimmed contents of some columns of a fixed-width line. Missing columns
+/// are treated as blank.
+fn column(line: &str, range: Range<usize>) -> &str {
+    let end = range.end.min(line.len());
+    line.get(range.start.min(end)..end)
+        .unwrap_or_default()
+        .trim()
+}
+
+fn parse_mjd(text: &str) -> Result<Mjd, PsruError> {
+    text.parse().map_err(|_| PsruError::TimMalformedMJD(None))
+}
+
+fn parse_column(
+    line: &str,
+    range: Range<usize>,
+    field: &'static str,
+) -> Result<f64, PsruError> {
+    let text = column(line, range);
+    parse_f64(text)
+        .map_err(|_| PsruError::TimBadColumn(None, field, text.to_string()))
+}
+
+/// Whether a word is a flag key rather than a value, i.e. `-fe` but not
+/// `-0.5`.
+fn is_flag_key(word: &str) -> bool {
+    word.strip_prefix('-')
+        .and_then(|rest| rest.chars().next())
+        .is_some_and(|c| c.is_ascii_alphabetic())
+}
+
+fn parse_empty_flag(key: &str) -> (String, Flag) {
+    (key[1..].to_string(), Flag::new(""))
 }
 
 fn parse_flag(key: &str, value: &str) -> (String, Flag) {
@@ -158,17 +462,79 @@ fn parse_flag(key: &str, value: &str) -> (String, Flag) {
         .strip_prefix('-')
         .map_or_else(|| key.to_string(), str::to_string);
 
-    let value = parse_f64(value)
-        .map_or_else(|_| Flag::String(value.to_string()), Flag::Double);
-
-    (key, value)
+    (key, Flag::new(value))
 }
 
-#[derive(Debug, PartialEq)]
-/// A TOA flag value.
+#[derive(Debug, Clone, PartialEq)]
+/// A TOA flag value. The text is kept as it was written, along with what it
+/// reads as, so that e.g. `-chan 007` is written back as `007`.
+///
+/// ```
+/// # use psrutils::timfile::Flag;
+/// let flag = Flag::new("007");
+/// assert_eq!(flag, Flag::Int(7, String::from("007")));
+/// assert_eq!(flag.as_f64(), Some(7.0));
+/// assert_eq!(flag.to_string(), "007");
+///
+/// assert_eq!(Flag::new("1e3").as_i64(), None);
+/// assert_eq!(Flag::new("L-wide").as_f64(), None);
+/// ```
 pub enum Flag {
-    /// Double precision value, or integers, if present.
-    Double(f64),
-    /// Anything that could not be cast to `f64`.
+    /// Integers that fit in `i64`, and the text.
+    Int(i64, String),
+    /// Other values that can be cast to `f64`, and the text.
+    Double(f64, String),
+    /// Anything that could not be cast to a number.
     String(String),
 }
+impl Flag {
+    /// Reads a value from its text, as an integer if possible, else as a
+    /// double, else as a string.
+    pub fn new(text: impl Into<String>) -> Self {
+        let text = text.into();
+        if let Ok(value) = text.parse() {
+            Self::Int(value, text)
+        } else if let Ok(value) = parse_f64(&text) {
+            Self::Double(value, text)
+        } else {
+            Self::String(text)
+        }
+    }
+
+    /// The text of the value, as written.
+    pub fn text(&self) -> &str {
+        match self {
+            Self::Int(_, text) | Self::Double(_, text) | Self::String(text) => {
+                text
+            }
+        }
+    }
+
+    /// The value, if it is an integer.
+    pub const fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Int(value, _) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value, if it is a number. Integers beyond 2^53 lose precision.
+    #[allow(clippy::cast_precision_loss)]
+    pub const fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Int(value, _) => Some(*value as f64),
+            Self::Double(value, _) => Some(*value),
+            Self::String(_) => None,
+        }
+    }
+
+    /// Whether the flag was written without a value.
+    pub fn is_empty(&self) -> bool {
+        self.text().is_empty()
+    }
+}
+impl std::fmt::Display for Flag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.text())
+    }
+}
@@ -0,0 +1,241 @@
+//! Writing TOAs back out to `.tim` files.
+
+use std::fmt::Write as _;
+use std::io::{BufWriter, Write};
+
+use super::{TOAInfo, TimFormat};
+use crate::data_types::Mjd;
+use crate::error::PsruError;
+
+/// Writes TOAs to a stream as a `.tim` file in the given format.
+///
+/// In Tempo2 format, the file starts with `FORMAT 1`, flags are written in
+/// alphabetical order, bad TOAs are commented out with `C`, and comments are
+/// kept at the end of each line. Flag values are written as they were read,
+/// and other numbers with as many digits as needed to read back the exact
+/// same values.
+///
+/// The fixed-width formats have no room for flags (other than `phase` for
+/// Parkes and `ddm` for Princeton and ITOA) or comments, which are left out.
+/// Their MJDs are written with all the digits the columns allow, which is
+/// enough to read back TOAs that came from such files unchanged.
+///
+/// Directives kept with the TOAs are written as comments before them, since
+/// their effects are already part of the values.
+///
+/// With [`TimFormat::Auto`], TOAs that were all read from the same
+/// fixed-width format are written in it, and anything else as Tempo2.
+///
+/// # Errors
+/// Most errors come from calls to `write_all`, but values that do not fit
+/// the columns of a fixed-width format also give errors.
+///
+/// # Examples
+/// ```
+/// # use psrutils::timfile::{TOAInfo, TimFormat, write_tim};
+/// let line = "C fname 1400.0 55000.1234567890123 1.5 pks -fe L-wide";
+/// let toas = vec![TOAInfo::from_line_tempo2(line).unwrap()];
+///
+/// let mut dst = Vec::new();
+/// write_tim(&mut dst, &toas, TimFormat::Tempo2).unwrap();
+///
+/// assert_eq!(
+///     String::from_utf8(dst).unwrap(),
+///     "FORMAT 1\nC fname 1400 55000.1234567890123 1.5 pks -fe L-wide\n"
+/// );
+/// ```
+pub fn write_tim(
+    writer: &mut impl Write,
+    toas: &[TOAInfo],
+    format: TimFormat,
+) -> Result<(), PsruError> {
+    let format = match format {
+        TimFormat::Auto => common_format(toas),
+        other => other,
+    };
+
+    let mut writer = BufWriter::new(writer);
+    if format == TimFormat::Tempo2 {
+        writer.write_all(b"FORMAT 1\n")?;
+    }
+
+    for toa in toas {
+        for directive in &toa.directives {
+            if !directive.trim_start().starts_with('#') {
+                writer.write_all(b"# ")?;
+            }
+            writer.write_all(directive.as_bytes())?;
+            writer.write_all(b"\n")?;
+        }
+        writer.write_all(toa.to_line(format)?.as_bytes())?;
+        writer.write_all(b"\n")?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+impl TOAInfo {
+    /// Renders the TOA as a single line (without newline) in the given
+    /// format. See [`write_tim`] for what each format keeps.
+    ///
+    /// # Errors
+    /// Gives errors for values that do not fit the columns of a fixed-width
+    /// format.
+    pub fn to_line(&self, format: TimFormat) -> Result<String, PsruError> {
+        match format {
+            TimFormat::Tempo2 => Ok(self.to_tempo2()),
+            TimFormat::Parkes => self.to_parkes(),
+            TimFormat::Princeton => self.to_princeton(),
+            TimFormat::Itoa => self.to_itoa(),
+            TimFormat::Auto => self.to_line(match self.format {
+                TimFormat::Auto => TimFormat::Tempo2,
+                other => other,
+            }),
+        }
+    }
+
+    fn to_tempo2(&self) -> String {
+        let mut line = format!(
+            "{}{} {} {} {} {}",
+            if self.is_bad { "C " } else { "" },
+            self.file,
+            self.frequency,
+            self.mjd,
+            self.mjd_error,
+            self.site_id,
+        );
+
+        // Flags without a value go last, for readers that would take the
+        // next word as the value
+        let mut keys = self.flags.keys().collect::<Vec<_>>();
+        keys.sort_by_key(|k| (self.flags[*k].is_empty(), *k));
+
+        for key in keys {
+            let value = &self.flags[key];
+            _ = if value.is_empty() {
+                write!(line, " -{key}")
+            } else {
+                write!(line, " -{key} {value}")
+            };
+        }
+
+        if !self.comment.is_empty() {
+            _ = write!(line, " # {}", self.comment);
+        }
+
+        line
+    }
+
+    fn to_parkes(&self) -> Result<String, PsruError> {
+        let (int, frac) = split_mjd(self.mjd, 7, 13)?;
+        let phase = self.flag_f64("phase").unwrap_or_default();
+
+        Ok(format!(
+            "{}{}{}{}.{}{}{}        {}",
+            if self.is_bad { 'C' } else { ' ' },
+            left("name", &self.file, 24)?,
+            fixed("frequency", self.frequency, 9)?,
+            int,
+            frac,
+            fixed("phase", phase, 8)?,
+            fixed("uncertainty", self.mjd_error, 8)?,
+            left("observatory", &self.site_id, 1)?,
+        ))
+    }
+
+    fn to_princeton(&self) -> Result<String, PsruError> {
+        let (int, frac) = split_mjd(self.mjd, 5, 14)?;
+        let site = left("observatory", &self.site_id, 1)?;
+        let head = if self.is_bad {
+            format!("C{site}")
+        } else {
+            format!("{site} ")
+        };
+
+        let mut line = format!(
+            "{head}{}{}{int}.{frac}{}",
+            left("name", &self.file, 13)?,
+            fixed("frequency", self.frequency, 9)?,
+            fixed("uncertainty", self.mjd_error, 9)?,
+        );
+        if let Some(ddm) = self.flag_f64("ddm") {
+            _ = write!(line, "{:15}{}", "", fixed("DM correction", ddm, 10)?);
+        }
+
+        Ok(line)
+    }
+
+    fn to_itoa(&self) -> Result<String, PsruError> {
+        let (int, frac) = split_mjd(self.mjd, 5, 13)?;
+        let (head, width) = if self.is_bad { ("C ", 7) } else { ("", 9) };
+        let ddm = match self.flag_f64("ddm") {
+            Some(ddm) => fixed("DM correction", ddm, 10)?,
+            None => format!("{:10}", ""),
+        };
+
+        Ok(format!(
+            "{head}{}{int}.{frac}{}{}{ddm}  {:>2}",
+            left("name", &self.file, width)?,
+            fixed("uncertainty", self.mjd_error, 6)?,
+            fixed("frequency", self.frequency, 11)?,
+            left("observatory", &self.site_id, 2)?.trim_end(),
+        ))
+    }
+}
+
+/// The format shared by all TOAs if it is a fixed-width one, else Tempo2.
+fn common_format(toas: &[TOAInfo]) -> TimFormat {
+    match toas.first().map(|t| t.format) {
+        Some(format)
+            if format != TimFormat::Auto
+                && toas.iter().all(|t| t.format == format) =>
+        {
+            format
+        }
+        _ => TimFormat::Tempo2,
+    }
+}
+
+/// The integer part of an MJD, right-aligned in `width` columns, and a fixed
+/// number of fractional digits, carrying over if the fraction rounds up to a
+/// whole day. Fails if the integer part does not fit.
+fn split_mjd(
+    mjd: Mjd,
+    width: usize,
+    digits: usize,
+) -> Result<(String, String), PsruError> {
+    let text = format!("{mjd:.digits$}");
+    let (int, frac) = text.split_once('.').unwrap_or((&text, ""));
+    if int.len() > width {
+        return Err(PsruError::TimBadColumn(None, "MJD", text));
+    }
+
+    Ok((format!("{int:>width$}"), frac.to_string()))
+}
+
+/// A value right-aligned in `width` columns, with as many decimals as fit.
+fn fixed(
+    field: &'static str,
+    value: f64,
+    width: usize,
+) -> Result<String, PsruError> {
+    (0..width)
+        .rev()
+        .map(|precision| format!("{value:>width$.precision$}"))
+        .find(|text| text.len() <= width)
+        .ok_or_else(|| PsruError::TimBadColumn(None, field, value.to_string()))
+}
+
+/// Text left-aligned in `width` columns.
+fn left(
+    field: &'static str,
+    text: &str,
+    width: usize,
+) -> Result<String, PsruError> {
+    if text.len() > width || !text.is_ascii() || text.contains(' ') {
+        return Err(PsruError::TimBadColumn(None, field, text.to_string()));
+    }
+
+    Ok(format!("{text:width$}"))
+}
@@ -0,0 +1,97 @@
+//! Warnings for recoverable oddities found while reading files.
+
+/// Something that did not stop a file from being read, but that the user
+/// might want to know about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    /// The file (or other source) the oddity was found in. May be empty if
+    /// the source was not named.
+    pub file: String,
+    /// The line number (starting at 1) where the oddity was found.
+    pub line: usize,
+    /// What kind of oddity was found.
+    pub kind: WarningKind,
+}
+impl Warning {
+    pub(crate) fn new(file: &str, line: usize, kind: WarningKind) -> Self {
+        Self {
+            file: file.to_string(),
+            line,
+            kind,
+        }
+    }
+}
+
+/// The kinds of oddities that produce warnings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WarningKind {
+    /// A `.par` key that is not recognised, but was kept anyway.
+    UnknownParameter(String),
+    /// A key was given with an alias that should no longer be used.
+    DeprecatedAlias {
+        /// The alias used.
+        alias: String,
+        /// The name it refers to.
+        name: String,
+    },
+    /// A parameter was repeated with the same value, and the repeat ignored.
+    DuplicateParameter(String),
+    /// A flag was given without a value.
+    EmptyFlag(String),
+    /// A directive that is known, but not acted upon, was skipped.
+    UnsupportedDirective(String),
+    /// A glitch was not defined enough, by its number, and was dropped.
+    IncompleteGlitch(usize),
+    /// A file was included again, and skipped.
+    RepeatedInclude(String),
+    /// A value is physically unlikely.
+    SuspiciousValue {
+        /// The name of the parameter, or field.
+        name: String,
+        /// Why it is suspicious.
+        reason: &'static str,
+    },
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.file.is_empty() {
+            write!(f, "On line {}: {}", self.line, self.kind)
+        } else {
+            write!(
+                f,
+                "In file '{}' on line {}: {}",
+                self.file, self.line, self.kind
+            )
+        }
+    }
+}
+impl std::fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownParameter(key) => {
+                write!(f, "Unrecognised key '{key}' was kept as-is.")
+            }
+            Self::DeprecatedAlias { alias, name } => {
+                write!(f, "Deprecated alias '{alias}' used for '{name}'.")
+            }
+            Self::DuplicateParameter(name) => {
+                write!(f, "Repeated '{name}' with the same value was ignored.")
+            }
+            Self::EmptyFlag(flag) => write!(f, "Flag '{flag}' has no value."),
+            Self::UnsupportedDirective(directive) => {
+                write!(f, "Directive '{directive}' is not supported, skipped.")
+            }
+            Self::IncompleteGlitch(number) => write!(
+                f,
+                "Glitch {number} lacks an epoch, GLF0 or GLF0D, dropped."
+            ),
+            Self::RepeatedInclude(name) => {
+                write!(f, "File '{name}' was already included, skipped.")
+            }
+            Self::SuspiciousValue { name, reason } => {
+                write!(f, "Suspicious value for '{name}': {reason}.")
+            }
+        }
+    }
+}